@@ -0,0 +1,282 @@
+use serde::{Serialize, Deserialize};
+use crate::stat::Stat;
+use crate::effect::Effects;
+use crate::consumable::ConsumablePlan;
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub enum UseRule {
+    OnTurn(u64),
+    OffCooldown,
+    BelowHp(f64),
+    HasEffect(String),
+}
+
+impl UseRule {
+    pub fn is_met(&self, turn: u64, hp: u64, max_hp: u64, effects: &Effects) -> bool {
+        match self {
+            UseRule::OnTurn(t) => *t == turn,
+            UseRule::OffCooldown => true,
+            UseRule::BelowHp(ratio) => (hp as f64) < ratio * max_hp as f64,
+            UseRule::HasEffect(id) => effects.has(id),
+        }
+    }
+
+    pub fn get_effect(&self) -> Option<&str> {
+        match self {
+            UseRule::HasEffect(id) => Some(id),
+            _ => None,
+        }
+    }
+}
+
+// an ability replacing the basic attack, resolved with the attacker stat boosted by its effect
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Ability {
+    damage_mult: Option<f64>,
+    guaranteed_crit: Option<bool>,
+    effect: Option<Stat>,
+}
+
+impl Ability {
+    pub fn apply(&self, stat: &Stat) -> Stat {
+        let mut strike = match &self.effect {
+            Some(e) => stat.clone() + e.clone(),
+            None => stat.clone(),
+        };
+        if let Some(m) = self.damage_mult {
+            strike.scale_damage(m);
+        }
+        if self.guaranteed_crit.unwrap_or(false) {
+            strike.force_crit();
+        }
+        strike
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum TurnAction<'a> {
+    BasicAttack,
+    Buff,
+    Consumable,
+    Ability(&'a Ability),
+    Stunned,
+    KnockedBack,
+}
+
+impl<'a> TurnAction<'a> {
+    // stat used to attack this turn, None if no attack is performed
+    pub fn strike_stat(&self, stat: &Stat) -> Option<Stat> {
+        match self {
+            TurnAction::BasicAttack => Some(stat.clone()),
+            TurnAction::Buff => None,
+            TurnAction::Consumable => None,
+            TurnAction::Ability(a) => Some(a.apply(stat)),
+            TurnAction::Stunned => None,
+            TurnAction::KnockedBack => None,
+        }
+    }
+
+    pub fn can_counter(&self) -> bool {
+        *self != TurnAction::Stunned
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct ActiveSkill {
+    cooldown: u64,
+    energy: u64,
+    self_effect: Option<String>,
+    other_effect: Option<String>,
+    attack: Option<Ability>,
+}
+
+impl ActiveSkill {
+    // effects the skill applies, to be found in effects.yaml
+    pub fn get_effects(&self) -> Vec<&str> {
+        self.self_effect.iter().chain(self.other_effect.iter()).map(|id| id.as_str()).collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct ActivePlan {
+    skill: String,
+    rules: Vec<UseRule>,
+}
+
+impl ActivePlan {
+    pub fn get_skill(&self) -> &str {
+        &self.skill
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct Action {
+    #[serde(default)]
+    start: Vec<String>,
+    #[serde(default)]
+    active: Vec<ActivePlan>,
+    #[serde(default)]
+    consumables: Vec<ConsumablePlan>,
+}
+
+impl Action {
+    pub fn get_start(&self) -> &[String] {
+        &self.start
+    }
+
+    pub fn get_active(&self) -> &[ActivePlan] {
+        &self.active
+    }
+
+    pub fn get_consumables(&self) -> &[ConsumablePlan] {
+        &self.consumables
+    }
+
+    // effects applied at the start or checked by a use rule, to be found in effects.yaml
+    pub fn get_effects(&self) -> Vec<&str> {
+        let rules = self.active.iter().flat_map(|p| p.rules.iter())
+            .chain(self.consumables.iter().flat_map(|p| p.get_rules().iter()));
+        self.start.iter().map(|id| id.as_str())
+            .chain(rules.filter_map(|r| r.get_effect()))
+            .collect()
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Active<'a> {
+    skill: &'a ActiveSkill,
+    rules: &'a [UseRule],
+    // first turn the skill can be used again
+    ready_turn: u64,
+}
+
+impl<'a> Active<'a> {
+    pub fn new(skill: &'a ActiveSkill, plan: &'a ActivePlan) -> Self {
+        Active {
+            skill,
+            rules: &plan.rules,
+            ready_turn: 0,
+        }
+    }
+
+    pub fn get_energy(&self) -> u64 {
+        self.skill.energy
+    }
+
+    pub fn get_self_effect(&self) -> Option<&'a str> {
+        self.skill.self_effect.as_deref()
+    }
+
+    pub fn get_other_effect(&self) -> Option<&'a str> {
+        self.skill.other_effect.as_deref()
+    }
+
+    pub fn get_action(&self) -> TurnAction<'a> {
+        match &self.skill.attack {
+            Some(a) => TurnAction::Ability(a),
+            None => TurnAction::Buff,
+        }
+    }
+
+    pub fn should_use(&self, turn: u64, hp: u64, max_hp: u64, energy: u64, effects: &Effects) -> bool {
+        if turn < self.ready_turn || energy < self.skill.energy {
+            return false;
+        }
+        // rules are alternatives, any of them triggers the skill
+        self.rules.iter().any(|r| r.is_met(turn, hp, max_hp, effects))
+    }
+
+    pub fn trigger(&mut self, turn: u64) {
+        self.ready_turn = turn + self.skill.cooldown;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn warcry() -> ActiveSkill {
+        ActiveSkill {
+            cooldown: 10,
+            energy: 5,
+            self_effect: Some(String::from("WarcrySelf")),
+            other_effect: Some(String::from("WarcryOther")),
+            attack: None,
+        }
+    }
+
+    #[test]
+    fn test_on_turn() {
+        let skill = warcry();
+        let plan = ActivePlan { skill: String::from("Warcry"), rules: vec![UseRule::OnTurn(3)] };
+        let active = Active::new(&skill, &plan);
+        assert!(!active.should_use(1, 100, 100, 100, &Effects::new()));
+        assert!(active.should_use(3, 100, 100, 100, &Effects::new()));
+        assert!(!active.should_use(4, 100, 100, 100, &Effects::new()));
+    }
+
+    #[test]
+    fn test_off_cooldown() {
+        let skill = warcry();
+        let plan = ActivePlan { skill: String::from("Warcry"), rules: vec![UseRule::OffCooldown] };
+        let mut active = Active::new(&skill, &plan);
+        assert!(active.should_use(1, 100, 100, 100, &Effects::new()));
+        active.trigger(1);
+        assert!(!active.should_use(2, 100, 100, 100, &Effects::new()));
+        assert!(!active.should_use(10, 100, 100, 100, &Effects::new()));
+        assert!(active.should_use(11, 100, 100, 100, &Effects::new()));
+    }
+
+    #[test]
+    fn test_below_hp() {
+        let skill = warcry();
+        let plan = ActivePlan { skill: String::from("Warcry"), rules: vec![UseRule::BelowHp(0.5)] };
+        let active = Active::new(&skill, &plan);
+        assert!(!active.should_use(1, 50, 100, 100, &Effects::new()));
+        assert!(active.should_use(1, 49, 100, 100, &Effects::new()));
+    }
+
+    #[test]
+    fn test_not_enough_energy() {
+        let skill = warcry();
+        let plan = ActivePlan { skill: String::from("Warcry"), rules: vec![UseRule::OffCooldown] };
+        let active = Active::new(&skill, &plan);
+        assert!(!active.should_use(1, 100, 100, 4, &Effects::new()));
+        assert!(active.should_use(1, 100, 100, 5, &Effects::new()));
+    }
+
+    #[test]
+    fn test_ability_strike() {
+        let stat: Stat = serde_yaml::from_str("weapon_dmg: 1.0\ncrit_chance: 0.25\narmor_pen: 0.25\n").unwrap();
+        let ability: Ability = serde_yaml::from_str(
+            "damage_mult: 1.5\nguaranteed_crit: true\neffect:\n  armor_pen: 0.25\n  bleed_chance: 0.5\n"
+        ).unwrap();
+        let expected: Stat = serde_yaml::from_str(
+            "weapon_dmg: 1.5\ncrit_chance: 1.0\narmor_pen: 0.5\nbleed_chance: 0.5\n"
+        ).unwrap();
+        assert_eq!(TurnAction::Ability(&ability).strike_stat(&stat), Some(expected));
+        assert_eq!(TurnAction::BasicAttack.strike_stat(&stat), Some(stat.clone()));
+        assert_eq!(TurnAction::Buff.strike_stat(&stat), None);
+    }
+
+    #[test]
+    fn test_deserialize_plan() {
+        let plan: ActivePlan = serde_yaml::from_str(
+            "skill: Warcry\nrules:\n  - !OnTurn 1\n  - OffCooldown\n  - !BelowHp 0.5\n  - !HasEffect Bleeding\n"
+        ).unwrap();
+        assert_eq!(
+            plan.rules, 
+            vec![UseRule::OnTurn(1), UseRule::OffCooldown, UseRule::BelowHp(0.5), UseRule::HasEffect(String::from("Bleeding"))]
+        );
+    }
+
+    #[test]
+    fn test_effects() {
+        let action: Action = serde_yaml::from_str(
+            "start: [Confusion]\nactive:\n  - skill: Warcry\n    rules:\n      - !HasEffect Daze\n      - OffCooldown\n\
+            consumables:\n  - item: Bandage\n    count: 1\n    rules:\n      - !HasEffect Bleeding\n"
+        ).unwrap();
+        assert_eq!(action.get_effects(), vec!["Confusion", "Daze", "Bleeding"]);
+        assert_eq!(warcry().get_effects(), vec!["WarcrySelf", "WarcryOther"]);
+    }
+}
@@ -0,0 +1,211 @@
+use std::fmt;
+use serde::{Serialize, Deserialize};
+use crate::{Data, simulate_damage_n_cycles, random};
+use crate::log::CombatLog;
+use crate::equipment::Character;
+use crate::ruleset::Ruleset;
+
+// a choice offered at level-up: passives learned and items equipped on top of the current character
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct Candidate {
+    name: String,
+    #[serde(default)]
+    passives: Vec<String>,
+    #[serde(default)]
+    equipment: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct Foe {
+    name: String,
+    weight: f64,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct Advisor {
+    character: String,
+    runs: u64,
+    // every candidate fights with the same random numbers, starting from this seed
+    #[serde(default)]
+    seed: u64,
+    candidates: Vec<Candidate>,
+    foes: Vec<Foe>,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Matchup {
+    win_rate: f64,
+    // mean hp left and mean number of turns to kill the foe, over the fights won
+    hp_left: f64,
+    turns: f64,
+    // mean ratio of the max hp kept, a lost fight keeping nothing
+    kept: f64,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Ranking {
+    candidate: String,
+    score: f64,
+    matchups: Vec<(String, Matchup)>,
+}
+
+// candidates sorted by score, best first
+#[derive(PartialEq, Debug, Clone)]
+pub struct Rankings(Vec<Ranking>);
+
+const MAX_TURN: u64 = 100;
+
+impl Advisor {
+    // the score is a mean weighted over the foes, which needs at least one foe and positive weights
+    pub fn validate(&self) -> Result<(), String> {
+        if self.foes.is_empty() {
+            return Err(String::from("The advisor needs at least one foe"));
+        }
+        for f in self.foes.iter() {
+            if f.weight.is_nan() || f.weight <= 0.0 {
+                return Err(format!("{}: the weight of a foe must be positive", f.name));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    fn matchup(&self, data: &Data, ruleset: &Ruleset, character: &Character, foe: &str) -> Result<Matchup, String> {
+        let foe_char = data.chars.get(foe).ok_or(format!("Unknown foe: {}", foe))?;
+        let ennemy = data.fighter(foe, foe_char, character, "other", ruleset)?;
+        let player = data.fighter("player", character, foe_char, "self", ruleset)?;
+        let max_hp = player.hp.max(1) as f64;
+
+        let (mut wins, mut hp_left, mut turns, mut kept) = (0, 0, 0, 0.0);
+        for i in 0..self.runs {
+            random::seed(self.seed.wrapping_add(i));
+            let result = simulate_damage_n_cycles(
+                &mut ennemy.clone(), &mut player.clone(), MAX_TURN, &data.effects, &mut CombatLog::new(false)
+            ).ok_or(format!("{}: the combat could not be simulated", foe))?;
+            if result.second_hp_at_end > 0 && result.first_hp_at_end == 0 {
+                wins += 1;
+                hp_left += result.second_hp_at_end;
+                turns += result.turn;
+                kept += result.second_hp_at_end as f64 / max_hp;
+            }
+        }
+        let runs = self.runs.max(1) as f64;
+        let won = wins.max(1) as f64;
+        Ok(Matchup {
+            win_rate: wins as f64 / runs,
+            hp_left: hp_left as f64 / won,
+            turns: turns as f64 / won,
+            kept: kept / runs,
+        })
+    }
+
+    // the score of a candidate is the weighted mean over the foes of the ratio of hp kept
+    pub fn rank(&self, data: &Data, ruleset: &Ruleset) -> Result<Rankings, String> {
+        let base = data.chars.get(&self.character).ok_or(format!("Unknown character: {}", self.character))?;
+        let total_weight: f64 = self.foes.iter().map(|f| f.weight).sum();
+        let mut rankings = Vec::new();
+        for c in self.candidates.iter() {
+            let character = base.with_loadout(&c.passives, &c.equipment, &data.items)
+                .map_err(|e| format!("{}: {}", c.name, e))?;
+            let mut matchups = Vec::new();
+            for f in self.foes.iter() {
+                matchups.push((f.name.clone(), self.matchup(data, ruleset, &character, &f.name)?));
+            }
+            let score = self.foes.iter().zip(matchups.iter())
+                .map(|(f, (_, m))| f.weight * m.kept)
+                .sum::<f64>() / total_weight;
+            rankings.push(Ranking { candidate: c.name.clone(), score, matchups });
+        }
+        rankings.sort_by(|a, b| b.score.total_cmp(&a.score));
+        Ok(Rankings(rankings))
+    }
+}
+
+impl fmt::Display for Rankings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<6}{:<24}{:<8}{:<16}{:<8}{:<10}{:<8}", "rank", "candidate", "score", "foe", "win", "hp left", "turns")?;
+        for (i, r) in self.0.iter().enumerate() {
+            for (j, (foe, m)) in r.matchups.iter().enumerate() {
+                let (rank, candidate, score) = if j == 0 {
+                    ((i + 1).to_string(), r.candidate.clone(), format!("{:.3}", r.score))
+                } else {
+                    (String::new(), String::new(), String::new())
+                };
+                writeln!(
+                    f, "{:<6}{:<24}{:<8}{:<16}{:<8}{:<10}{:<8}",
+                    rank, candidate, score, foe,
+                    format!("{:.1}%", m.win_rate * 100.0), format!("{:.1}", m.hp_left), format!("{:.1}", m.turns)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn advisor(character: &str, candidates: &str) -> Advisor {
+        serde_yaml::from_str(&format!(
+            "character: {}\nruns: 200\nseed: 7\ncandidates:\n{}foes:\n  - name: crawler\n    weight: 1.0\n", character, candidates
+        )).unwrap()
+    }
+
+    #[test]
+    fn test_common_random_numbers() {
+        // the same loadout fights the exact same combats
+        let data = Data::load(None).unwrap();
+        let rankings = advisor("main", "  - name: first\n  - name: second\n").rank(&data, data.ruleset("classic").unwrap()).unwrap();
+        assert_eq!(rankings.0[0].matchups, rankings.0[1].matchups);
+        assert_eq!(rankings.0[0].score, rankings.0[1].score);
+    }
+
+    #[test]
+    fn test_ranking() {
+        let data = Data::load(None).unwrap();
+        // the shield blocks part of the attacks the current loadout takes in full
+        let rankings = advisor(
+            "main_rot", "  - name: current\n  - name: shield\n    equipment: [Shield]\n"
+        ).rank(&data, data.ruleset("classic").unwrap()).unwrap();
+        assert_eq!(rankings.0[0].candidate, "shield");
+        assert!(rankings.0[0].score > rankings.0[1].score);
+        assert_eq!(rankings.0.len(), 2);
+        let table = rankings.to_string();
+        assert!(table.starts_with("rank"));
+        assert_eq!(table.lines().count(), 3);
+    }
+
+    #[test]
+    fn test_unknown_names() {
+        let data = Data::load(None).unwrap();
+        let ruleset = data.ruleset("classic").unwrap();
+        let unknown_item = advisor("main", "  - name: armored\n    equipment: [Plate]\n");
+        assert_eq!(unknown_item.rank(&data, ruleset), Err(String::from("armored: Unknown item: Plate")));
+        let mut unknown_foe = advisor("main", "  - name: current\n");
+        unknown_foe.foes[0].name = String::from("dragon");
+        assert_eq!(unknown_foe.rank(&data, ruleset), Err(String::from("Unknown foe: dragon")));
+        let mut unknown_character = advisor("main", "  - name: current\n");
+        unknown_character.character = String::from("hero");
+        assert_eq!(unknown_character.rank(&data, ruleset), Err(String::from("Unknown character: hero")));
+        let learned = advisor("main", "  - name: nimble\n    passives: [Disengage]\n");
+        assert_eq!(learned.rank(&data, ruleset), Err(String::from("nimble: Passive already learned: Disengage")));
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut advisor = advisor("main", "  - name: current\n");
+        assert_eq!(advisor.validate(), Ok(()));
+        advisor.foes[0].weight = 0.0;
+        assert_eq!(advisor.validate(), Err(String::from("crawler: the weight of a foe must be positive")));
+        advisor.foes.clear();
+        assert_eq!(advisor.validate(), Err(String::from("The advisor needs at least one foe")));
+    }
+}
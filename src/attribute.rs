@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+use serde::{Serialize, Deserialize};
+use crate::stat::Stat;
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Eq, Hash, Clone, Copy)]
+pub enum Attribute {
+    Strength,
+    Agility,
+    Perception,
+    Vitality,
+    Willpower,
+}
+
+const ATTRIBUTES: [Attribute; 5] = [
+    Attribute::Strength,
+    Attribute::Agility,
+    Attribute::Perception,
+    Attribute::Vitality,
+    Attribute::Willpower,
+];
+
+// value of an attribute giving no bonus, every character starting with it
+const BASE_ATTRIBUTE: u64 = 10;
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub struct Attributes {
+    strength: Option<u64>,
+    agility: Option<u64>,
+    perception: Option<u64>,
+    vitality: Option<u64>,
+    willpower: Option<u64>,
+}
+
+impl Attributes {
+    fn get(&self, attribute: Attribute) -> u64 {
+        match attribute {
+            Attribute::Strength => self.strength,
+            Attribute::Agility => self.agility,
+            Attribute::Perception => self.perception,
+            Attribute::Vitality => self.vitality,
+            Attribute::Willpower => self.willpower,
+        }.unwrap_or(BASE_ATTRIBUTE)
+    }
+
+    // each point above the base adds the per point bonus of the attribute once
+    pub fn derive(&self, per_point: &HashMap<Attribute, Stat>) -> Stat {
+        let mut stat = Stat::default();
+        for attribute in ATTRIBUTES {
+            let bonus = match per_point.get(&attribute) {
+                Some(b) => b,
+                None => continue,
+            };
+            for _ in 0..self.get(attribute).saturating_sub(BASE_ATTRIBUTE) {
+                stat += bonus.clone();
+            }
+        }
+        stat
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn per_point() -> HashMap<Attribute, Stat> {
+        serde_yaml::from_str(
+            "Agility:\n  accuracy: 0.02\n  dodge: 0.01\nVitality:\n  hp: 5\nPerception:\n  crit_chance: 0.01\n"
+        ).unwrap()
+    }
+
+    #[test]
+    fn test_base_attributes() {
+        let attributes: Attributes = serde_yaml::from_str("agility: 10\nvitality: 8\n").unwrap();
+        assert_eq!(attributes.derive(&per_point()), Stat::default());
+    }
+
+    #[test]
+    fn test_derive() {
+        let attributes: Attributes = serde_yaml::from_str("agility: 12\nvitality: 13\n").unwrap();
+        let expected: Stat = serde_yaml::from_str("accuracy: 0.04\ndodge: 0.02\nhp: 15\n").unwrap();
+        assert_eq!(attributes.derive(&per_point()), expected);
+    }
+}
@@ -0,0 +1,116 @@
+use serde::{Serialize, Deserialize};
+use crate::active::UseRule;
+use crate::effect::Effects;
+
+// an item used instead of attacking, applying a timed effect and curing others
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct Consumable {
+    effect: Option<String>,
+    #[serde(default)]
+    cure: Vec<String>,
+}
+
+impl Consumable {
+    pub fn get_effect(&self) -> Option<&str> {
+        self.effect.as_deref()
+    }
+
+    pub fn get_cure(&self) -> &[String] {
+        &self.cure
+    }
+
+    // effects applied or cured, to be found in effects.yaml
+    pub fn get_effects(&self) -> Vec<&str> {
+        self.effect.iter().chain(self.cure.iter()).map(|id| id.as_str()).collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct ConsumablePlan {
+    item: String,
+    count: u64,
+    rules: Vec<UseRule>,
+}
+
+impl ConsumablePlan {
+    pub fn get_item(&self) -> &str {
+        &self.item
+    }
+
+    pub fn get_rules(&self) -> &[UseRule] {
+        &self.rules
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Item<'a> {
+    name: &'a str,
+    consumable: &'a Consumable,
+    rules: &'a [UseRule],
+    // uses left in the inventory
+    count: u64,
+}
+
+impl<'a> Item<'a> {
+    pub fn new(consumable: &'a Consumable, plan: &'a ConsumablePlan) -> Self {
+        Item {
+            name: &plan.item,
+            consumable,
+            rules: &plan.rules,
+            count: plan.count,
+        }
+    }
+
+    pub fn get_name(&self) -> &'a str {
+        self.name
+    }
+
+    pub fn get_consumable(&self) -> &'a Consumable {
+        self.consumable
+    }
+
+    pub fn should_use(&self, turn: u64, hp: u64, max_hp: u64, effects: &Effects) -> bool {
+        self.count > 0 && self.rules.iter().any(|r| r.is_met(turn, hp, max_hp, effects))
+    }
+
+    pub fn consume(&mut self) {
+        self.count = self.count.saturating_sub(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effect::Skill;
+
+    fn potion() -> Consumable {
+        serde_yaml::from_str("effect: HealingPotion\n").unwrap()
+    }
+
+    #[test]
+    fn test_limited_uses() {
+        let potion = potion();
+        let plan: ConsumablePlan = serde_yaml::from_str("item: HealingPotion\ncount: 2\nrules:\n  - !BelowHp 0.35\n").unwrap();
+        let mut item = Item::new(&potion, &plan);
+        let effects = Effects::new();
+        assert!(!item.should_use(1, 35, 100, &effects));
+        assert!(item.should_use(1, 34, 100, &effects));
+        item.consume();
+        item.consume();
+        assert!(!item.should_use(1, 34, 100, &effects));
+    }
+
+    #[test]
+    fn test_use_when_affected() {
+        let bandage: Consumable = serde_yaml::from_str("cure:\n  - Bleeding\n").unwrap();
+        let plan: ConsumablePlan = serde_yaml::from_str("item: Bandage\ncount: 1\nrules:\n  - !HasEffect Bleeding\n").unwrap();
+        let bleeding: Skill = serde_yaml::from_str("id: Bleeding\npermanent: true\neffect: {}\n").unwrap();
+        let item = Item::new(&bandage, &plan);
+        let mut effects = Effects::new();
+        assert!(!item.should_use(1, 100, 100, &effects));
+        effects.add(&bleeding);
+        assert!(item.should_use(1, 100, 100, &effects));
+        assert_eq!(item.get_consumable().get_cure(), &[String::from("Bleeding")]);
+        assert_eq!(item.get_consumable().get_effect(), None);
+    }
+}
@@ -0,0 +1,149 @@
+use crate::stat::BuiltinEffect;
+use crate::effect::Effects;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Control {
+    Free,
+    Dazed,
+    Stunned,
+    Immune,
+}
+
+impl Control {
+    // a stunned character can't attack nor counter
+    pub fn can_act(&self) -> bool {
+        *self != Control::Stunned
+    }
+
+    // a dazed character can't use its active skills
+    pub fn can_use_skills(&self) -> bool {
+        *self == Control::Free || *self == Control::Immune
+    }
+
+    // effect actually applied when a control effect lands, None if it's ignored
+    pub fn on_apply<'a>(&self, id: &'a str) -> Option<&'a str> {
+        match (self, BuiltinEffect::from_id(id)) {
+            (Control::Immune, Some(BuiltinEffect::Daze)) => None,
+            (Control::Immune, Some(BuiltinEffect::Stun)) => None,
+            (Control::Dazed, Some(BuiltinEffect::Daze)) => Some(BuiltinEffect::Stun.id()),
+            (Control::Stunned, Some(BuiltinEffect::Daze)) => None,
+            _ => Some(id),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub struct ControlState {
+    // turns left during which control effects are ignored
+    immunity: u64,
+}
+
+impl ControlState {
+    pub fn new() -> Self {
+        ControlState { immunity: 0 }
+    }
+
+    // immunity ignores the control effects landing, not the ones already there
+    pub fn get(&self, effects: &Effects) -> Control {
+        if effects.has(BuiltinEffect::Stun.id()) {
+            Control::Stunned
+        } else if effects.has(BuiltinEffect::Daze.id()) {
+            Control::Dazed
+        } else if self.immunity > 0 {
+            Control::Immune
+        } else {
+            Control::Free
+        }
+    }
+
+    // effect actually applied when a control effect lands, a daze never escalating into a stun during immunity
+    pub fn on_apply<'a>(&self, effects: &Effects, id: &'a str) -> Option<&'a str> {
+        if self.immunity > 0 {
+            Control::Immune.on_apply(id)
+        } else {
+            self.get(effects).on_apply(id)
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.immunity = self.immunity.saturating_sub(1);
+    }
+
+    pub fn start_immunity(&mut self, turns: u64) {
+        self.immunity = self.immunity.max(turns);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effect::Skill;
+
+    fn daze() -> Skill {
+        serde_yaml::from_str("id: Daze\nduration: 5\neffect:\n  move_res: -0.25\n").unwrap()
+    }
+
+    fn stun() -> Skill {
+        serde_yaml::from_str("id: Stun\nduration: 2\nimmunity: 3\neffect:\n  dodge: -10\n").unwrap()
+    }
+
+    #[test]
+    fn test_daze_escalates_into_stun() {
+        assert_eq!(Control::Free.on_apply("Daze"), Some("Daze"));
+        assert_eq!(Control::Dazed.on_apply("Daze"), Some("Stun"));
+        assert_eq!(Control::Stunned.on_apply("Daze"), None);
+        assert_eq!(Control::Dazed.on_apply("Stun"), Some("Stun"));
+    }
+
+    #[test]
+    fn test_immune() {
+        assert_eq!(Control::Immune.on_apply("Daze"), None);
+        assert_eq!(Control::Immune.on_apply("Stun"), None);
+        assert_eq!(Control::Immune.on_apply("Bleeding"), Some("Bleeding"));
+        assert_eq!(Control::Immune.on_apply("Rage"), Some("Rage"));
+    }
+
+    #[test]
+    fn test_action_denial() {
+        assert!(Control::Free.can_act() && Control::Free.can_use_skills());
+        assert!(Control::Dazed.can_act() && !Control::Dazed.can_use_skills());
+        assert!(!Control::Stunned.can_act() && !Control::Stunned.can_use_skills());
+        assert!(Control::Immune.can_act() && Control::Immune.can_use_skills());
+    }
+
+    #[test]
+    fn test_state() {
+        let (daze, stun) = (daze(), stun());
+        let mut effects = Effects::new();
+        let mut state = ControlState::new();
+        assert_eq!(state.get(&effects), Control::Free);
+        effects.add(&daze);
+        assert_eq!(state.get(&effects), Control::Dazed);
+        effects.add(&stun);
+        assert_eq!(state.get(&effects), Control::Stunned);
+        state.start_immunity(2);
+        assert_eq!(state.get(&effects), Control::Stunned);
+        effects.remove("Stun");
+        effects.remove("Daze");
+        assert_eq!(state.get(&effects), Control::Immune);
+        state.tick();
+        state.tick();
+        assert_eq!(state.get(&effects), Control::Free);
+    }
+
+    #[test]
+    fn test_dazed_while_immune() {
+        let daze = daze();
+        let mut effects = Effects::new();
+        let mut state = ControlState::new();
+        effects.add(&daze);
+        state.start_immunity(3);
+        // the daze still denies skills, but a new daze doesn't escalate into a stun
+        assert_eq!(state.get(&effects), Control::Dazed);
+        assert!(!state.get(&effects).can_use_skills());
+        assert_eq!(state.on_apply(&effects, "Daze"), None);
+        assert_eq!(state.on_apply(&effects, "Bleeding"), Some("Bleeding"));
+        effects.remove("Daze");
+        assert_eq!(state.get(&effects), Control::Immune);
+    }
+}
@@ -0,0 +1,583 @@
+use std::{collections::HashMap, ops::Index};
+use serde::{Serialize, Deserialize};
+use crate::stat::{Stat, Dot, Merge, SECONDARY_EFFECTS};
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub enum Stacking {
+    // a new application adds a stack and resets the duration
+    RefreshDuration,
+    // a new application extends the duration, without adding a stack
+    AddDuration,
+    // a new application adds a stack, keeping the current duration
+    AddIntensity,
+    // each application is tracked separately with its own duration
+    Independent,
+}
+
+// what happens when a knockback pushes the target against an obstacle
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub struct Collision {
+    obstacle_chance: f64,
+    damage: u64,
+    stun_chance: f64,
+}
+
+impl Collision {
+    pub fn get_obstacle_chance(&self) -> f64 {
+        self.obstacle_chance
+    }
+
+    pub fn get_damage(&self) -> u64 {
+        self.damage
+    }
+
+    pub fn get_stun_chance(&self) -> f64 {
+        self.stun_chance
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub enum ScalingSource {
+    // the character who applied the effect, as it was when applying it
+    Caster,
+    // the character under the effect, before any effect
+    Owner,
+}
+
+// a bonus growing with a stat: the effect is given once per point of the source stat weighted by per
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Scaling {
+    from: ScalingSource,
+    per: Stat,
+    effect: Stat,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct Skill {
+    id: String,
+    // number of turns the effect lasts, counting the turn it's applied on
+    duration: Option<u64>,
+    permanent: Option<bool>,
+    max_stack: Option<u64>,
+    stacking: Option<Stacking>,
+    // turns of immunity to control effects once the effect ends
+    immunity: Option<u64>,
+    collision: Option<Collision>,
+    dot: Option<Dot>,
+    // how the effect combines with the stat, added by default
+    #[serde(default)]
+    merge: Merge,
+    effect: Stat,
+    // bonus worth a percentage of the stat before any effect, 10 giving 10% of the base value
+    percent: Option<Stat>,
+    #[serde(default)]
+    scaling: Vec<Scaling>,
+}
+
+impl Skill {
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn get_immunity(&self) -> u64 {
+        self.immunity.unwrap_or(0)
+    }
+
+    pub fn get_collision(&self) -> Option<Collision> {
+        self.collision
+    }
+
+    pub fn get_dot(&self) -> Option<Dot> {
+        self.dot
+    }
+
+    fn get_max_stack(&self) -> u64 {
+        self.max_stack.unwrap_or(1).max(1)
+    }
+
+    fn get_stacking(&self) -> Stacking {
+        self.stacking.unwrap_or(Stacking::RefreshDuration)
+    }
+
+    fn get_duration(&self) -> u64 {
+        self.duration.unwrap_or(0)
+    }
+
+    fn is_permanent(&self) -> bool {
+        self.permanent.unwrap_or(false)
+    }
+
+    // an effect lasting no turn would vanish before doing anything
+    fn validate(&self) -> Result<(), String> {
+        if !self.is_permanent() && self.get_duration() == 0 {
+            return Err(format!("Effect {} needs a duration or permanent: true", self.id));
+        }
+        Ok(())
+    }
+
+    // effects not added are applied multipliers first, then caps and finally overrides,
+    // the id breaking ties so that the result doesn't depend on the order effects were applied in
+    fn get_order(&self) -> (u8, &str) {
+        let rank = match self.merge {
+            Merge::Add => 0,
+            Merge::Multiply => 1,
+            Merge::Min => 2,
+            Merge::Max => 3,
+            Merge::Override => 4,
+        };
+        (rank, &self.id)
+    }
+}
+
+// every effect of effects.yaml, found by its id
+#[derive(Serialize, Deserialize, PartialEq, Debug, Default)]
+#[serde(transparent)]
+pub struct Registry {
+    effects: HashMap<String, Skill>,
+}
+
+impl Registry {
+    pub fn get(&self, id: &str) -> Result<&Skill, String> {
+        self.effects.get(id).ok_or(format!("Unknown effect: {}", id))
+    }
+
+    // each effect must be registered under its own id with a duration, and the effects applied by the engine must all be registered
+    pub fn validate(&self) -> Result<(), String> {
+        for (key, skill) in self.effects.iter() {
+            if *key != skill.id {
+                return Err(format!("Effect {} registered under {}", skill.id, key));
+            }
+            skill.validate()?;
+        }
+        for builtin in SECONDARY_EFFECTS {
+            self.get(builtin.id())?;
+        }
+        Ok(())
+    }
+}
+
+// ids are validated at load, an unknown one is a bug
+impl Index<&str> for Registry {
+    type Output = Skill;
+
+    fn index(&self, id: &str) -> &Skill {
+        &self.effects[id]
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct EffectInstance<'a> {
+    skill: &'a Skill,
+    // stat of the character who applied the effect, if any
+    caster: Option<Stat>,
+    stacks: u64,
+    applied_turn: u64,
+    remaining: u64,
+}
+
+impl<'a> EffectInstance<'a> {
+    fn new(skill: &'a Skill, turn: u64, caster: Option<&Stat>) -> Self {
+        EffectInstance {
+            skill,
+            caster: caster.cloned(),
+            stacks: 1,
+            applied_turn: turn,
+            remaining: skill.get_duration(),
+        }
+    }
+
+    fn is_outdated(&self) -> bool {
+        !self.skill.is_permanent() && self.remaining == 0
+    }
+
+    // additive part of one stack: the effect if it's added, the ratio of the base stat and the scaled bonuses
+    fn bonus(&self, base: &Stat) -> Stat {
+        let mut bonus = Stat::default();
+        if self.skill.merge == Merge::Add {
+            bonus += self.skill.effect.clone();
+        }
+        if let Some(ratios) = &self.skill.percent {
+            bonus += base.percent_of(ratios);
+        }
+        for scaling in self.skill.scaling.iter() {
+            let source = match scaling.from {
+                ScalingSource::Caster => self.caster.as_ref().unwrap_or(base),
+                ScalingSource::Owner => base,
+            };
+            bonus += scaling.effect.scale(source.weigh(&scaling.per));
+        }
+        bonus
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Effects<'a> {
+    instances: Vec<EffectInstance<'a>>,
+    // current turn of the combat, starting at 1
+    turn: u64,
+}
+
+impl<'a> Effects<'a> {
+    pub fn new() -> Self {
+        Effects { instances: Vec::new(), turn: 1 }
+    }
+
+    #[cfg(test)]
+    pub fn add(&mut self, skill: &'a Skill) {
+        self.cast(skill, None);
+    }
+
+    // adds an effect applied by a character, whose stat the effect may scale with
+    pub fn cast(&mut self, skill: &'a Skill, caster: Option<&Stat>) {
+        let max_stack = skill.get_max_stack();
+
+        if skill.get_stacking() == Stacking::Independent {
+            let count = self.instances.iter().filter(|x| x.skill.id == skill.id).count() as u64;
+            if count >= max_stack {
+                // the oldest application is replaced by the new one
+                let oldest = self.instances.iter().enumerate()
+                    .filter(|(_, x)| x.skill.id == skill.id)
+                    .min_by_key(|(_, x)| x.applied_turn)
+                    .map(|(i, _)| i)
+                    .unwrap();
+                self.instances.remove(oldest);
+            }
+            self.instances.push(EffectInstance::new(skill, self.turn, caster));
+            return;
+        }
+
+        let instance = match self.instances.iter_mut().find(|x| x.skill.id == skill.id) {
+            Some(i) => i,
+            None => {
+                self.instances.push(EffectInstance::new(skill, self.turn, caster));
+                return;
+            },
+        };
+        match skill.get_stacking() {
+            Stacking::RefreshDuration => {
+                instance.stacks = max_stack.min(instance.stacks + 1);
+                instance.applied_turn = self.turn;
+                instance.remaining = skill.get_duration();
+                instance.caster = caster.cloned();
+            },
+            Stacking::AddDuration => {
+                instance.remaining += skill.get_duration();
+            },
+            Stacking::AddIntensity => {
+                instance.stacks = max_stack.min(instance.stacks + 1);
+            },
+            Stacking::Independent => (),
+        }
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        self.instances.retain(|x| x.skill.id != id);
+    }
+
+    // ends the current turn, each effect loses a turn of duration independently
+    // returns the skills whose effect ended
+    pub fn tick(&mut self) -> Vec<&'a Skill> {
+        for instance in self.instances.iter_mut() {
+            instance.remaining = instance.remaining.saturating_sub(1);
+        }
+        let ended = self.instances.iter().filter(|x| x.is_outdated()).map(|x| x.skill).collect();
+        self.instances.retain(|x| !x.is_outdated());
+        self.turn += 1;
+        ended
+    }
+
+    pub fn has(&self, id: &str) -> bool {
+        self.instances.iter().any(|x| x.skill.id == id)
+    }
+
+    // damage over time effects, each independent instance ticking on its own
+    pub fn get_dots(&self) -> Vec<(&'a Skill, Dot, u64)> {
+        self.instances.iter()
+            .filter_map(|x| x.skill.get_dot().map(|d| (x.skill, d, x.stacks)))
+            .collect()
+    }
+
+    #[cfg(test)]
+    pub fn get_stacks(&self, id: &str) -> u64 {
+        self.instances.iter().filter(|x| x.skill.id == id).map(|x| x.stacks).sum()
+    }
+
+    // each stack applies the effect of the skill once: the additive bonuses come first, 
+    // then the other effects following their order
+    pub fn apply(&self, stat: &Stat) -> Stat {
+        let mut bonus = Stat::default();
+        for instance in self.instances.iter() {
+            for _ in 0..instance.stacks {
+                bonus += instance.bonus(stat);
+            }
+        }
+        let mut raw_stat: Stat = stat.clone().with_bonus(bonus);
+        let mut instances: Vec<&EffectInstance> = self.instances.iter()
+            .filter(|x| x.skill.merge != Merge::Add)
+            .collect();
+        instances.sort_by(|a, b| a.skill.get_order().cmp(&b.skill.get_order()));
+        for instance in instances {
+            for _ in 0..instance.stacks {
+                raw_stat = raw_stat.merge(instance.skill.effect.clone(), instance.skill.merge);
+            }
+        }
+        raw_stat
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn skill(stacking: Stacking, max_stack: u64, duration: u64) -> Skill {
+        Skill {
+            id: String::from("Bleeding"),
+            duration: Some(duration),
+            permanent: None,
+            max_stack: Some(max_stack),
+            stacking: Some(stacking),
+            immunity: None,
+            collision: None,
+            dot: None,
+            merge: Merge::Add,
+            effect: serde_yaml::from_str("block_power: 2\n").unwrap(),
+            percent: None,
+            scaling: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_default_replaces() {
+        let skill: Skill = serde_yaml::from_str("id: Daze\nduration: 5\neffect:\n  move_res: -0.25\n").unwrap();
+        let mut effects = Effects::new();
+        effects.add(&skill);
+        effects.add(&skill);
+        assert_eq!(effects.get_stacks("Daze"), 1);
+    }
+
+    #[test]
+    fn test_refresh_duration() {
+        let skill = skill(Stacking::RefreshDuration, 3, 5);
+        let mut effects = Effects::new();
+        for _ in 0..5 {
+            effects.add(&skill);
+        }
+        assert_eq!(effects.get_stacks("Bleeding"), 3);
+        assert_eq!(effects.instances.len(), 1);
+    }
+
+    #[test]
+    fn test_add_duration() {
+        let skill = skill(Stacking::AddDuration, 3, 5);
+        let mut effects = Effects::new();
+        effects.add(&skill);
+        effects.add(&skill);
+        assert_eq!(effects.get_stacks("Bleeding"), 1);
+        assert_eq!(effects.instances[0].remaining, 10);
+    }
+
+    #[test]
+    fn test_add_intensity() {
+        let skill = skill(Stacking::AddIntensity, 2, 5);
+        let mut effects = Effects::new();
+        for _ in 0..3 {
+            effects.add(&skill);
+        }
+        assert_eq!(effects.get_stacks("Bleeding"), 2);
+        assert_eq!(effects.instances[0].remaining, 5);
+    }
+
+    #[test]
+    fn test_independent() {
+        let skill = skill(Stacking::Independent, 2, 5);
+        let mut effects = Effects::new();
+        for _ in 0..3 {
+            effects.add(&skill);
+        }
+        assert_eq!(effects.get_stacks("Bleeding"), 2);
+        assert_eq!(effects.instances.len(), 2);
+    }
+
+    #[test]
+    fn test_independent_replaces_oldest() {
+        let skill = skill(Stacking::Independent, 2, 5);
+        let mut effects = Effects::new();
+        effects.add(&skill);
+        effects.tick();
+        effects.add(&skill);
+        effects.add(&skill);
+        assert_eq!(effects.instances.iter().map(|x| x.applied_turn).collect::<Vec<u64>>(), vec![2, 2]);
+    }
+
+    #[test]
+    fn test_duration_is_relative() {
+        let skill: Skill = serde_yaml::from_str("id: Daze\nduration: 5\neffect:\n  move_res: -0.25\n").unwrap();
+        let mut effects = Effects::new();
+        for _ in 0..6 {
+            effects.tick();
+        }
+        effects.add(&skill);
+        assert_eq!(effects.instances[0].applied_turn, 7);
+        for _ in 0..4 {
+            effects.tick();
+            assert_eq!(effects.get_stacks("Daze"), 1);
+        }
+        effects.tick();
+        assert_eq!(effects.get_stacks("Daze"), 0);
+    }
+
+    #[test]
+    fn test_refresh_resets_duration() {
+        let skill = skill(Stacking::RefreshDuration, 1, 3);
+        let mut effects = Effects::new();
+        effects.add(&skill);
+        effects.tick();
+        effects.tick();
+        effects.add(&skill);
+        effects.tick();
+        effects.tick();
+        assert_eq!(effects.get_stacks("Bleeding"), 1);
+        effects.tick();
+        assert_eq!(effects.get_stacks("Bleeding"), 0);
+    }
+
+    #[test]
+    fn test_permanent() {
+        let skill: Skill = serde_yaml::from_str("id: Immobilization\npermanent: true\neffect:\n  dodge: -0.25\n").unwrap();
+        let mut effects = Effects::new();
+        effects.add(&skill);
+        for _ in 0..100 {
+            effects.tick();
+        }
+        assert_eq!(effects.get_stacks("Immobilization"), 1);
+    }
+
+    #[test]
+    fn test_missing_duration() {
+        let skill: Skill = serde_yaml::from_str("id: Daze\neffect:\n  move_res: -0.25\n").unwrap();
+        assert_eq!(skill.validate(), Err(String::from("Effect Daze needs a duration or permanent: true")));
+        let skill: Skill = serde_yaml::from_str("id: Daze\npermanent: false\neffect:\n  move_res: -0.25\n").unwrap();
+        assert!(skill.validate().is_err());
+    }
+
+    #[test]
+    fn test_collision() {
+        let skill: Skill = serde_yaml::from_str(
+            "id: Knockback\nduration: 2\ncollision:\n  obstacle_chance: 0.25\n  damage: 5\n  stun_chance: 0.5\neffect: {}\n"
+        ).unwrap();
+        let collision = skill.get_collision().unwrap();
+        assert_eq!(collision.get_obstacle_chance(), 0.25);
+        assert_eq!(collision.get_damage(), 5);
+        assert_eq!(collision.get_stun_chance(), 0.5);
+    }
+
+    #[test]
+    fn test_apply_scales_with_stacks() {
+        let skill = skill(Stacking::AddIntensity, 3, 5);
+        let mut effects = Effects::new();
+        for _ in 0..3 {
+            effects.add(&skill);
+        }
+        let stat: Stat = serde_yaml::from_str("block_power: 1\n").unwrap();
+        let expected: Stat = serde_yaml::from_str("block_power: 7\n").unwrap();
+        assert_eq!(effects.apply(&stat), expected);
+    }
+
+    #[test]
+    fn test_bonus_on_unset_multiplier() {
+        // a healing reduction on a profile without healing_received starts from the neutral 1.0
+        let skill: Skill = serde_yaml::from_str("id: Poisoning\nduration: 5\neffect:\n  healing_received: -0.25\n").unwrap();
+        let mut effects = Effects::new();
+        effects.add(&skill);
+        let stat: Stat = serde_yaml::from_str("hp: 50\n").unwrap();
+        assert_eq!(effects.apply(&stat).healing(8.0), 6.0);
+    }
+
+    #[test]
+    fn test_dots() {
+        let bleed: Skill = serde_yaml::from_str(
+            "id: Bleeding\npermanent: true\nmax_stack: 3\nstacking: Independent\ndot:\n  damage_type: Bleed\n  flat: 3\neffect: {}\n"
+        ).unwrap();
+        let poison: Skill = serde_yaml::from_str(
+            "id: Poisoning\nduration: 5\nmax_stack: 3\nstacking: AddIntensity\ndot:\n  damage_type: Poison\n  flat: 2\neffect: {}\n"
+        ).unwrap();
+        let mut effects = Effects::new();
+        effects.add(&bleed);
+        effects.add(&bleed);
+        effects.add(&poison);
+        effects.add(&poison);
+        let dots: Vec<(&str, u64)> = effects.get_dots().iter().map(|(s, _, n)| (s.get_id(), *n)).collect();
+        assert_eq!(dots, vec![("Bleeding", 1), ("Bleeding", 1), ("Poisoning", 2)]);
+    }
+
+    #[test]
+    fn test_registry() {
+        let builtins: String = SECONDARY_EFFECTS.iter()
+            .map(|e| format!("{}:\n  id: {}\n  duration: 1\n  effect: {{}}\n", e.id(), e.id()))
+            .collect();
+        let registry: Registry = serde_yaml::from_str(&format!("{}Rage:\n  id: Rage\n  permanent: true\n  effect: {{}}\n", builtins)).unwrap();
+        assert_eq!(registry.validate(), Ok(()));
+        assert_eq!(registry.get("Rage").map(|s| s.get_id()), Ok("Rage"));
+        assert_eq!(registry.get("Frenzy").map(|s| s.get_id()), Err(String::from("Unknown effect: Frenzy")));
+    }
+
+    #[test]
+    fn test_invalid_registry() {
+        let missing: Registry = serde_yaml::from_str("Daze:\n  id: Daze\n  duration: 1\n  effect: {}\n").unwrap();
+        assert_eq!(missing.validate(), Err(String::from("Unknown effect: Bleeding")));
+        let mismatch: Registry = serde_yaml::from_str("Daze:\n  id: Stun\n  duration: 1\n  effect: {}\n").unwrap();
+        assert_eq!(mismatch.validate(), Err(String::from("Effect Stun registered under Daze")));
+        let vanishing: Registry = serde_yaml::from_str("Daze:\n  id: Daze\n  effect: {}\n").unwrap();
+        assert_eq!(vanishing.validate(), Err(String::from("Effect Daze needs a duration or permanent: true")));
+    }
+
+    #[test]
+    fn test_merge_order() {
+        let bonus: Skill = serde_yaml::from_str("id: Bonus\nduration: 5\neffect:\n  dodge: 0.5\n").unwrap();
+        let half: Skill = serde_yaml::from_str("id: Half\nduration: 5\nmerge: Multiply\neffect:\n  dodge: 0.5\n").unwrap();
+        let mace: Skill = serde_yaml::from_str("id: Mace\nduration: 5\nmerge: Override\neffect:\n  weapon_type: Mace\n").unwrap();
+        let spear: Skill = serde_yaml::from_str("id: Spear\nduration: 5\nmerge: Override\neffect:\n  weapon_type: Spear\n").unwrap();
+        let stat: Stat = serde_yaml::from_str("dodge: 0.5\nweapon_type: Sword\n").unwrap();
+        let expected: Stat = serde_yaml::from_str("dodge: 0.5\nweapon_type: Spear\n").unwrap();
+
+        let mut effects = Effects::new();
+        for skill in [&spear, &half, &mace, &bonus] {
+            effects.add(skill);
+        }
+        let mut reversed = Effects::new();
+        for skill in [&bonus, &mace, &half, &spear] {
+            reversed.add(skill);
+        }
+        assert_eq!(effects.apply(&stat), expected);
+        assert_eq!(reversed.apply(&stat), expected);
+    }
+
+    #[test]
+    fn test_added_on_unset_field() {
+        let ward: Skill = serde_yaml::from_str("id: Ward\nduration: 5\neffect:\n  damage_taken: -0.25\n").unwrap();
+        let mut effects = Effects::new();
+        effects.add(&ward);
+        effects.add(&ward);
+        let stat: Stat = serde_yaml::from_str("hp: 50\n").unwrap();
+        let expected: Stat = serde_yaml::from_str("hp: 50\ndamage_taken: 0.75\n").unwrap();
+        assert_eq!(effects.apply(&stat), expected);
+    }
+
+    #[test]
+    fn test_percent_and_scaling() {
+        let frenzy: Skill = serde_yaml::from_str(
+            "id: Frenzy\nduration: 5\neffect: {}\npercent:\n  block: 50\nscaling:\n  \
+            - from: Caster\n    per:\n      dodge: 1\n    effect:\n      crit_chance: 0.5\n  \
+            - from: Owner\n    per:\n      counter: 1\n    effect:\n      fumble: 1\n"
+        ).unwrap();
+        let half: Skill = serde_yaml::from_str("id: Half\nduration: 5\nmerge: Multiply\neffect:\n  block: 0.5\n").unwrap();
+        let stat: Stat = serde_yaml::from_str("block: 0.5\ncounter: 0.25\n").unwrap();
+        let caster: Stat = serde_yaml::from_str("dodge: 0.5\n").unwrap();
+        let mut effects = Effects::new();
+        effects.add(&half);
+        effects.cast(&frenzy, Some(&caster));
+        // the bonuses are added before the block is halved
+        let expected: Stat = serde_yaml::from_str("block: 0.375\ncounter: 0.25\ncrit_chance: 0.25\nfumble: 0.25\n").unwrap();
+        assert_eq!(effects.apply(&stat), expected);
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use serde::{Serialize, Deserialize};
+use crate::stat::Stat;
+use crate::attribute::{Attribute, Attributes};
+use crate::passive::Catalog;
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub enum Slot {
+    Weapon,
+    Head,
+    Chest,
+    Gloves,
+    Boots,
+    Shield,
+    Ring,
+    Amulet,
+}
+
+impl Slot {
+    // number of items of this slot a character can wear at once
+    fn capacity(&self) -> usize {
+        match self {
+            Slot::Ring => 2,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct Equipment {
+    slot: Slot,
+    stat: Stat,
+}
+
+// a base profile plus its primary attributes, its passives and the items it wears
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Character {
+    #[serde(flatten)]
+    stat: Stat,
+    attributes: Option<Attributes>,
+    #[serde(default)]
+    passives: Vec<String>,
+    #[serde(default)]
+    equipment: Vec<String>,
+}
+
+impl Character {
+    pub fn get_passives(&self) -> &[String] {
+        &self.passives
+    }
+
+    // the same character with more passives, each new item replacing a worn one of the same slot if none is free
+    pub fn with_loadout(
+        &self, passives: &[String], equipment: &[String], items: &HashMap<String, Equipment>
+    ) -> Result<Character, String> {
+        let mut character = self.clone();
+        for name in passives.iter() {
+            if character.passives.contains(name) {
+                return Err(format!("Passive already learned: {}", name));
+            }
+            character.passives.push(name.clone());
+        }
+        for name in equipment.iter() {
+            let slot = items.get(name).ok_or(format!("Unknown item: {}", name))?.slot;
+            let worn: Vec<usize> = character.equipment.iter().enumerate()
+                .filter(|(_, e)| items.get(*e).map(|i| i.slot) == Some(slot))
+                .map(|(i, _)| i)
+                .collect();
+            if worn.len() >= slot.capacity() {
+                character.equipment.remove(worn[0]);
+            }
+            character.equipment.push(name.clone());
+        }
+        Ok(character)
+    }
+
+    // adds the stats derived from the attributes, the self effect of the passives 
+    // and the stat of each equipped item onto the base profile
+    pub fn build(
+        &self, items: &HashMap<String, Equipment>, per_point: &HashMap<Attribute, Stat>, catalog: &Catalog
+    ) -> Result<Stat, String> {
+        for (i, name) in self.passives.iter().enumerate() {
+            if self.passives[..i].contains(name) {
+                return Err(format!("Passive learned twice: {}", name));
+            }
+        }
+        let mut stat = self.stat.clone();
+        if let Some(a) = self.attributes {
+            stat = stat.with_bonus(a.derive(per_point));
+        }
+        stat = stat.with_bonus(catalog.self_effect(&self.passives)?);
+        let mut worn: Vec<Slot> = Vec::new();
+        for name in self.equipment.iter() {
+            let item = items.get(name).ok_or(format!("Unknown item: {}", name))?;
+            if worn.iter().filter(|s| **s == item.slot).count() >= item.slot.capacity() {
+                return Err(format!("No free {:?} slot for {}", item.slot, name));
+            }
+            worn.push(item.slot);
+            stat = stat.with_bonus(item.stat.clone());
+        }
+        Ok(stat)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items() -> HashMap<String, Equipment> {
+        serde_yaml::from_str(
+            "Sword:\n  slot: Weapon\n  stat:\n    damage:\n      - [Slash, 21]\n    weapon_type: Sword\n\
+            Helmet:\n  slot: Head\n  stat:\n    head:\n      protection: 5\n\
+            Ring:\n  slot: Ring\n  stat:\n    crit_chance: 0.05\n"
+        ).unwrap()
+    }
+
+    #[test]
+    fn test_build() {
+        let character: Character = serde_yaml::from_str(
+            "hp: 100\ncrit_chance: 0.1\nequipment:\n  - Sword\n  - Helmet\n  - Ring\n  - Ring\n"
+        ).unwrap();
+        let expected: Stat = serde_yaml::from_str(
+            "hp: 100\ncrit_chance: 0.2\ndamage:\n  - [Slash, 21]\nweapon_type: Sword\nhead:\n  protection: 5\n"
+        ).unwrap();
+        assert_eq!(character.build(&items(), &HashMap::new(), &Catalog::default()), Ok(expected));
+    }
+
+    #[test]
+    fn test_no_equipment() {
+        let character: Character = serde_yaml::from_str("hp: 100\n").unwrap();
+        let expected: Stat = serde_yaml::from_str("hp: 100\n").unwrap();
+        assert_eq!(character.build(&items(), &HashMap::new(), &Catalog::default()), Ok(expected));
+    }
+
+    #[test]
+    fn test_attributes() {
+        let character: Character = serde_yaml::from_str(
+            "hp: 100\nattributes:\n  vitality: 12\nequipment:\n  - Helmet\n"
+        ).unwrap();
+        let per_point = serde_yaml::from_str("Vitality:\n  hp: 4\n").unwrap();
+        let expected: Stat = serde_yaml::from_str("hp: 108\nhead:\n  protection: 5\n").unwrap();
+        assert_eq!(character.build(&items(), &per_point, &Catalog::default()), Ok(expected));
+    }
+
+    #[test]
+    fn test_passives() {
+        let character: Character = serde_yaml::from_str("dodge: 0.25\npassives:\n  - Disengage\n").unwrap();
+        let catalog = serde_yaml::from_str("Athletics:\n  Disengage:\n    self_effect:\n      dodge: 0.5\n").unwrap();
+        let expected: Stat = serde_yaml::from_str("dodge: 0.75\n").unwrap();
+        assert_eq!(character.build(&items(), &HashMap::new(), &catalog), Ok(expected));
+    }
+
+    #[test]
+    fn test_loadout() {
+        let character: Character = serde_yaml::from_str(
+            "equipment:\n  - Helmet\n  - Ring\n  - Ring\n"
+        ).unwrap();
+        let mut items = items();
+        items.insert(String::from("Hood"), serde_yaml::from_str("slot: Head\nstat: {}\n").unwrap());
+        let loadout = character.with_loadout(
+            &[String::from("Disengage")], &[String::from("Hood"), String::from("Ring")], &items
+        ).unwrap();
+        assert_eq!(loadout.get_passives(), &[String::from("Disengage")]);
+        assert_eq!(loadout.equipment, vec!["Ring", "Hood", "Ring"]);
+        assert_eq!(
+            loadout.with_loadout(&[String::from("Disengage")], &[], &items),
+            Err(String::from("Passive already learned: Disengage"))
+        );
+    }
+
+    #[test]
+    fn test_invalid_equipment() {
+        let unknown: Character = serde_yaml::from_str("equipment:\n  - Axe\n").unwrap();
+        assert_eq!(unknown.build(&items(), &HashMap::new(), &Catalog::default()), Err(String::from("Unknown item: Axe")));
+        let twice: Character = serde_yaml::from_str("equipment:\n  - Helmet\n  - Helmet\n").unwrap();
+        assert_eq!(twice.build(&items(), &HashMap::new(), &Catalog::default()), Err(String::from("No free Head slot for Helmet")));
+        let learned_twice: Character = serde_yaml::from_str("passives:\n  - Disengage\n  - Disengage\n").unwrap();
+        assert_eq!(
+            learned_twice.build(&items(), &HashMap::new(), &Catalog::default()),
+            Err(String::from("Passive learned twice: Disengage"))
+        );
+    }
+}
@@ -1,5 +1,7 @@
-use rand::{self, Rng};
+use serde::{Serialize, Deserialize};
+use crate::random;
 
+#[allow(clippy::enum_variant_names)]
 #[derive(PartialEq, Debug, Clone, Copy)]
 pub enum HitType {
     CritHit,
@@ -11,7 +13,7 @@ pub enum HitType {
     NoHit,
 }
 
-#[derive(PartialEq, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
 pub enum BodyPart {
     RightLeg,
     LeftLeg,
@@ -22,6 +24,94 @@ pub enum BodyPart {
     None,
 }
 
+// relative chance for each body part to be hit
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub struct HitLocation {
+    right_leg: f64,
+    left_leg: f64,
+    right_hand: f64,
+    left_hand: f64,
+    torso: f64,
+    head: f64,
+}
+
+impl HitLocation {
+    fn weights(&self) -> [(BodyPart, f64); 6] {
+        [
+            (BodyPart::RightLeg, self.right_leg),
+            (BodyPart::LeftLeg, self.left_leg),
+            (BodyPart::RightHand, self.right_hand),
+            (BodyPart::LeftHand, self.left_hand),
+            (BodyPart::Torso, self.torso),
+            (BodyPart::Head, self.head),
+        ]
+    }
+
+    fn total(&self) -> f64 {
+        self.weights().iter().map(|(_, w)| w.max(0.0)).sum()
+    }
+
+    // moves a ratio of the weight of every other body part onto the aimed one
+    pub fn aim(&self, part: BodyPart, shift: f64) -> Self {
+        let shift = shift.clamp(0.0, 1.0);
+        let mut location = *self;
+        for (p, w) in self.weights() {
+            let weight = if p == part { w + shift * (self.total() - w) } else { w * (1.0 - shift) };
+            match p {
+                BodyPart::RightLeg => location.right_leg = weight,
+                BodyPart::LeftLeg => location.left_leg = weight,
+                BodyPart::RightHand => location.right_hand = weight,
+                BodyPart::LeftHand => location.left_hand = weight,
+                BodyPart::Torso => location.torso = weight,
+                BodyPart::Head => location.head = weight,
+                BodyPart::None => (),
+            }
+        }
+        location
+    }
+
+    pub fn draw(&self) -> BodyPart {
+        let total = self.total();
+        if total <= 0.0 {
+            return BodyPart::None;
+        }
+        let mut random_value: f64 = random::uniform() * total;
+        for (p, w) in self.weights() {
+            if random_value < w.max(0.0) {
+                return p;
+            }
+            random_value -= w.max(0.0);
+        }
+        BodyPart::Torso
+    }
+
+    #[cfg(test)]
+    pub fn get_chance(&self, part: BodyPart) -> f64 {
+        self.weights().iter().filter(|(p, _)| *p == part).map(|(_, w)| w.max(0.0)).sum::<f64>() / self.total()
+    }
+}
+
+// size of the target, large beasts are less often hit on the head
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Default)]
+pub enum Size {
+    #[default]
+    Medium,
+    Large,
+}
+
+impl Size {
+    pub fn hit_location(&self) -> HitLocation {
+        match self {
+            Size::Medium => HitLocation {
+                right_leg: 0.15, left_leg: 0.15, right_hand: 0.15, left_hand: 0.15, torso: 0.3, head: 0.1,
+            },
+            Size::Large => HitLocation {
+                right_leg: 0.2, left_leg: 0.2, right_hand: 0.1, left_hand: 0.1, torso: 0.35, head: 0.05,
+            },
+        }
+    }
+}
+
 #[derive(PartialEq, Debug, Clone, Copy)]
 pub struct Chance {
     crit_hit: f64,
@@ -52,13 +142,9 @@ impl Chance {
     }
 
     pub fn draw(&self, input_proba: Option<f64>) -> HitType {
-        let mut rng = rand::thread_rng();
-        let random_value: f64 = rng.gen_range(0.0..1.0);
+        let random_value: f64 = random::uniform();
 
-        let added_proba = match input_proba {
-            Some(c) => c,
-            None => 1.0,
-        };
+        let added_proba = input_proba.unwrap_or(1.0);
 
         if self.crit_hit * added_proba > random_value  {
             HitType::CritHit
@@ -110,6 +196,7 @@ impl Hit {
         self.chance.draw(added_proba)
     }
 
+    #[cfg(test)]
     pub fn get_chance(&self) -> Chance {
         self.chance
     }
@@ -221,4 +308,33 @@ mod tests {
             assert_ne!(chance.draw(None), HitType::NoHit);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_default_location() {
+        let location = Size::default().hit_location();
+        assert!((location.get_chance(BodyPart::Head) - 0.1).abs() < 1e-9);
+        assert!((location.get_chance(BodyPart::Torso) - 0.3).abs() < 1e-9);
+        assert!(Size::Large.hit_location().get_chance(BodyPart::Head) < location.get_chance(BodyPart::Head));
+    }
+
+    #[test]
+    fn test_aim() {
+        let location = Size::Medium.hit_location().aim(BodyPart::Head, 0.5);
+        assert!((location.get_chance(BodyPart::Head) - 0.55).abs() < 1e-9);
+        assert!((location.get_chance(BodyPart::Torso) - 0.15).abs() < 1e-9);
+        let location = Size::Medium.hit_location().aim(BodyPart::Torso, 1.0);
+        for _ in 0..100 {
+            assert_eq!(location.draw(), BodyPart::Torso);
+        }
+    }
+
+    #[test]
+    fn test_location_weights() {
+        let location: HitLocation = serde_yaml::from_str(
+            "right_leg: 0\nleft_leg: 0\nright_hand: 0\nleft_hand: 0\ntorso: 0\nhead: 3\n"
+        ).unwrap();
+        for _ in 0..100 {
+            assert_eq!(location.draw(), BodyPart::Head);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use std::fmt;
+use crate::stat::EffectRoll;
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Event<'a> {
+    Effect {
+        target: &'a str,
+        roll: EffectRoll,
+        success: bool,
+        // effect actually applied, it may differ from the rolled one (daze becoming a stun)
+        applied: Option<&'a str>,
+    },
+    Collision {
+        target: &'a str,
+        damage: u64,
+    },
+    LostAction {
+        target: &'a str,
+    },
+    Dot {
+        target: &'a str,
+        id: &'a str,
+        damage: u64,
+    },
+    Consumable {
+        target: &'a str,
+        item: &'a str,
+    },
+}
+
+// where the damage taken by a character comes from
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum DamageSource<'a> {
+    Attack,
+    Counter,
+    Collision,
+    Dot(&'a str),
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum HealSource {
+    Regeneration,
+    Lifesteal,
+}
+
+impl fmt::Display for HealSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HealSource::Regeneration => write!(f, "regeneration"),
+            HealSource::Lifesteal => write!(f, "lifesteal"),
+        }
+    }
+}
+
+impl<'a> fmt::Display for DamageSource<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DamageSource::Attack => write!(f, "attacks"),
+            DamageSource::Counter => write!(f, "counters"),
+            DamageSource::Collision => write!(f, "collisions"),
+            DamageSource::Dot(id) => write!(f, "{}", id),
+        }
+    }
+}
+
+impl<'a> fmt::Display for Event<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::Effect { target, roll, success, applied } => {
+                write!(f, "{:?} on {} ({:.1}%): ", roll.get_id(), target, roll.probability() * 100.0)?;
+                match (success, applied) {
+                    (false, _) => write!(f, "resisted"),
+                    (true, None) => write!(f, "ignored"),
+                    (true, Some(id)) if *id == roll.get_id().id() => write!(f, "applied"),
+                    (true, Some(id)) => write!(f, "applied as {}", id),
+                }
+            },
+            Event::Collision { target, damage } => write!(f, "{} is knocked back into an obstacle and takes {} damage", target, damage),
+            Event::LostAction { target } => write!(f, "{} loses its action to the knockback", target),
+            Event::Dot { target, id, damage } => write!(f, "{} takes {} damage from {}", target, damage, id),
+            Event::Consumable { target, item } => write!(f, "{} uses {}", target, item),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct CombatLog<'a> {
+    enabled: bool,
+    turn: u64,
+    entries: Vec<(u64, Event<'a>)>,
+    // total damage taken by each character from each source
+    breakdown: Vec<(&'a str, DamageSource<'a>, u64)>,
+    // total hp restored to each character from each source
+    healing: Vec<(&'a str, HealSource, u64)>,
+}
+
+impl<'a> CombatLog<'a> {
+    pub fn new(enabled: bool) -> Self {
+        CombatLog {
+            enabled,
+            turn: 1,
+            entries: Vec::new(),
+            breakdown: Vec::new(),
+            healing: Vec::new(),
+        }
+    }
+
+    pub fn set_turn(&mut self, turn: u64) {
+        self.turn = turn;
+    }
+
+    pub fn push(&mut self, event: Event<'a>) {
+        if self.enabled {
+            self.entries.push((self.turn, event));
+        }
+    }
+
+    pub fn add_damage(&mut self, target: &'a str, source: DamageSource<'a>, damage: u64) {
+        if !self.enabled || damage == 0 {
+            return;
+        }
+        match self.breakdown.iter_mut().find(|(t, s, _)| *t == target && *s == source) {
+            Some((_, _, total)) => *total += damage,
+            None => self.breakdown.push((target, source, damage)),
+        }
+    }
+
+    pub fn add_healing(&mut self, target: &'a str, source: HealSource, amount: u64) {
+        if !self.enabled || amount == 0 {
+            return;
+        }
+        match self.healing.iter_mut().find(|(t, s, _)| *t == target && *s == source) {
+            Some((_, _, total)) => *total += amount,
+            None => self.healing.push((target, source, amount)),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn get_events(&self) -> Vec<&Event<'a>> {
+        self.entries.iter().map(|(_, e)| e).collect()
+    }
+}
+
+impl<'a> fmt::Display for CombatLog<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (turn, event) in self.entries.iter() {
+            writeln!(f, "turn {}: {}", turn, event)?;
+        }
+        if !self.breakdown.is_empty() {
+            writeln!(f, "damage breakdown:")?;
+        }
+        for (target, source, damage) in self.breakdown.iter() {
+            writeln!(f, "{} took {} damage from {}", target, damage, source)?;
+        }
+        if !self.healing.is_empty() {
+            writeln!(f, "healing breakdown:")?;
+        }
+        for (target, source, amount) in self.healing.iter() {
+            writeln!(f, "{} healed {} hp from {}", target, amount, source)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stat::Stat;
+    use crate::hit::BodyPart;
+
+    fn bleed_roll() -> EffectRoll {
+        let attacker: Stat = serde_yaml::from_str("bleed_chance: 0.5\n").unwrap();
+        let target: Stat = serde_yaml::from_str("torso:\n  bleed_res: 0.5\n").unwrap();
+        attacker.effect_table(&target, BodyPart::Torso, false)[0]
+    }
+
+    #[test]
+    fn test_disabled_log() {
+        let mut log = CombatLog::new(false);
+        log.push(Event::Effect { target: "bear", roll: bleed_roll(), success: true, applied: Some("Bleeding") });
+        assert!(log.get_events().is_empty());
+    }
+
+    #[test]
+    fn test_display() {
+        let mut log = CombatLog::new(true);
+        log.set_turn(3);
+        log.push(Event::Effect { target: "bear", roll: bleed_roll(), success: true, applied: Some("Bleeding") });
+        log.push(Event::Effect { target: "bear", roll: bleed_roll(), success: false, applied: None });
+        assert_eq!(log.get_events().len(), 2);
+        assert_eq!(
+            log.to_string(), 
+            "turn 3: Bleeding on bear (25.0%): applied\nturn 3: Bleeding on bear (25.0%): resisted\n"
+        );
+    }
+
+    #[test]
+    fn test_breakdown() {
+        let mut log = CombatLog::new(true);
+        log.push(Event::Dot { target: "bear", id: "Bleeding", damage: 3 });
+        log.add_damage("bear", DamageSource::Attack, 20);
+        log.add_damage("bear", DamageSource::Dot("Bleeding"), 3);
+        log.add_damage("bear", DamageSource::Attack, 10);
+        log.add_damage("bear", DamageSource::Counter, 0);
+        assert_eq!(
+            log.to_string(),
+            "turn 1: bear takes 3 damage from Bleeding\ndamage breakdown:\nbear took 30 damage from attacks\nbear took 3 damage from Bleeding\n"
+        );
+    }
+
+    #[test]
+    fn test_healing_breakdown() {
+        let mut log = CombatLog::new(true);
+        log.add_healing("bear", HealSource::Regeneration, 2);
+        log.add_healing("bear", HealSource::Lifesteal, 4);
+        log.add_healing("bear", HealSource::Regeneration, 2);
+        assert_eq!(
+            log.to_string(),
+            "healing breakdown:\nbear healed 4 hp from regeneration\nbear healed 4 hp from lifesteal\n"
+        );
+    }
+}
@@ -1,54 +1,278 @@
 mod stat;
 mod hit;
+mod active;
+mod effect;
+mod control;
+mod log;
+mod consumable;
+mod equipment;
+mod attribute;
+mod passive;
+mod random;
+mod advisor;
+mod profile;
+mod ruleset;
+mod version;
 
-use serde::{Serialize, Deserialize};
-use serde_yaml;
 use std::{fs::File, collections::HashMap, ops::{AddAssign, Add}};
-use stat::{Stat, IdSkills};
+use serde::de::DeserializeOwned;
+use serde_yaml::Mapping;
+use stat::{Stat, BuiltinEffect};
 use hit::{Hit, HitType, BodyPart};
-
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
-struct Skill {
-    id: IdSkills,
-    turn: u64,
-    effect: Stat,
-}
+use active::{Action, Active, ActiveSkill, TurnAction};
+use effect::{Effects, Skill, Registry};
+use control::{Control, ControlState};
+use log::{CombatLog, Event, DamageSource, HealSource};
+use consumable::{Consumable, Item};
+use equipment::{Character, Equipment};
+use attribute::Attribute;
+use passive::{Catalog, Trigger, TriggerTarget};
+use advisor::Advisor;
+use ruleset::Ruleset;
+use version::GameVersion;
 
 #[derive(PartialEq, Debug)]
 struct Char<'a> {
+    name: &'a str,
     stat: Stat,
-    skills: HashMap<IdSkills, &'a Skill>,
+    ruleset: &'a Ruleset,
+    skills: Effects<'a>,
+    actives: Vec<Active<'a>>,
+    items: Vec<Item<'a>>,
+    // effects applied by the passives when landing a hit
+    triggers: Vec<Trigger>,
+    hp: u64,
+    energy: u64,
+    block: u64,
+    control: ControlState,
+    // total hp restored during the combat
+    healed: u64,
 }
 
 impl<'a> Char<'a> {
+    fn new(name: &'a str, stat: Stat, ruleset: &'a Ruleset) -> Self {
+        let hp = stat.get_hp().unwrap_or(0);
+        let energy = stat.get_energy().unwrap_or(0);
+        let block = stat.get_block().unwrap_or(0);
+        Char {
+            name,
+            stat,
+            ruleset,
+            skills: Effects::new(),
+            actives: Vec::new(),
+            items: Vec::new(),
+            triggers: Vec::new(),
+            hp,
+            energy,
+            block,
+            control: ControlState::new(),
+            healed: 0,
+        }
+    }
+
     fn clone(&self) -> Self {
         Char {
+            name: self.name,
             stat: self.stat.clone(),
+            ruleset: self.ruleset,
             skills: self.skills.clone(),
+            actives: self.actives.clone(),
+            items: self.items.clone(),
+            triggers: self.triggers.clone(),
+            hp: self.hp,
+            energy: self.energy,
+            block: self.block,
+            control: self.control,
+            healed: self.healed,
         }
     }
 
+    // the stat the character fights with: its effects applied, then bounded by the caps of the ruleset
     fn compute(&self)-> Stat {
-        let mut raw_stat: Stat = self.stat.clone();
-        for (_, s) in self.skills.iter() {
-            raw_stat += s.effect.clone();
+        let mut stat = self.skills.apply(&self.stat);
+        stat.deplete_block(self.block);
+        self.ruleset.resolve(&stat)
+    }
+
+    fn get_control(&self) -> Control {
+        self.control.get(&self.skills)
+    }
+
+    fn tick_skills(&mut self) {
+        self.control.tick();
+        for s in self.skills.tick() {
+            self.control.start_immunity(s.get_immunity());
+        }
+    }
+
+    // control effects go through the control state first, a daze on a dazed character becomes a stun
+    // caster is the stat of the character applying the effect, if any
+    // returns the effect actually applied, if any
+    fn add_skill(&mut self, skill: &'a Skill, skills_map: &'a Registry, caster: Option<&Stat>) -> Option<&'a str> {
+        let id = self.control.on_apply(&self.skills, skill.get_id())?;
+        if id == skill.get_id() {
+            self.skills.cast(skill, caster);
+        } else {
+            self.skills.remove(skill.get_id());
+            self.skills.cast(&skills_map[id], caster);
+        }
+        Some(id)
+    }
+
+    fn take_damage(&mut self, damage: f64) {
+        self.hp = self.hp.saturating_sub(damage as u64);
+    }
+
+    // hp never goes above the max hp of the character
+    fn heal(&mut self, amount: f64, source: HealSource, log: &mut CombatLog<'a>) {
+        let max_hp = self.stat.get_hp().unwrap_or(0);
+        let healed = (self.compute().healing(amount) as u64).min(max_hp.saturating_sub(self.hp));
+        self.hp += healed;
+        self.healed += healed;
+        log.add_healing(self.name, source, healed);
+    }
+
+    fn regen_hp(&mut self, log: &mut CombatLog<'a>) {
+        let max_hp = self.stat.get_hp().unwrap_or(0);
+        let regen = max_hp as f64 * self.compute().get_hp_regen().unwrap_or(0.0);
+        self.heal(regen, HealSource::Regeneration, log);
+    }
+
+    // a knocked back character loses its next attack or counter
+    fn lose_action(&mut self, log: &mut CombatLog<'a>) -> bool {
+        if !self.skills.has(BuiltinEffect::Knockback.id()) {
+            return false;
+        }
+        self.skills.remove(BuiltinEffect::Knockback.id());
+        log.push(Event::LostAction { target: self.name });
+        true
+    }
+
+    // a knockback against an obstacle deals collision damage and may stun
+    fn collide(&mut self, skill: &'a Skill, skills_map: &'a Registry, bodypart_hit: BodyPart, log: &mut CombatLog<'a>) {
+        let collision = match skill.get_collision() {
+            Some(c) => c,
+            None => return,
+        };
+        if random::uniform() >= collision.get_obstacle_chance() {
+            return;
+        }
+        self.take_damage(collision.get_damage() as f64);
+        log.push(Event::Collision { target: self.name, damage: collision.get_damage() });
+        log.add_damage(self.name, DamageSource::Collision, collision.get_damage());
+
+        let roll = self.compute().resist_roll(BuiltinEffect::Stun, collision.get_stun_chance(), bodypart_hit);
+        let success = random::uniform() < roll.probability();
+        let applied = if success { self.add_skill(&skills_map[BuiltinEffect::Stun.id()], skills_map, None) } else { None };
+        log.push(Event::Effect { target: self.name, roll, success, applied });
+    }
+
+    // each damage over time effect ticks on its own, resisted by the matching resistance
+    fn tick_dots(&mut self, log: &mut CombatLog<'a>) {
+        let stat = self.compute();
+        for (skill, dot, stacks) in self.skills.get_dots() {
+            let damage = stat.dot_damage(&dot, stacks, self.hp) as u64;
+            self.take_damage(damage as f64);
+            log.push(Event::Dot { target: self.name, id: skill.get_id(), damage });
+            log.add_damage(self.name, DamageSource::Dot(skill.get_id()), damage);
         }
-        raw_stat
     }
 
-    fn remove_outdated_skills(&mut self, turn: &u64) {
-        self.skills.retain(|_, x| x.turn > *turn || x.turn == 0);
+    fn damage_block(&mut self, damage: f64) {
+        self.block = self.block.saturating_sub(damage as u64);
     }
 
-    fn add_skill(&mut self, skill: &'a Skill) {
-        self.skills.insert(skill.id, skill);
+    // a ratio of the max block power is recovered each turn, even from a depleted block
+    fn recover_block(&mut self) {
+        let max_block = self.stat.get_block().unwrap_or(0);
+        let recovery = (max_block as f64 * self.stat.get_block_recovery().unwrap_or(0.0)) as u64;
+        self.block = max_block.min(self.block + recovery);
     }
 
-    fn resolve_hit(&self, other: &mut Char<'a>, skills_map: &'a HashMap<IdSkills, Skill>, bodypart_hit :BodyPart, is_crit: bool) {
-        let hm = self.stat.additional_effect( &other.stat, bodypart_hit, is_crit);
-        for (s, b) in hm.iter() {
-            if *b {
-                other.add_skill(&skills_map[s]);
+    fn regen_energy(&mut self) {
+        let max_energy = self.stat.get_energy().unwrap_or(0);
+        let regen = (max_energy as f64 * self.stat.get_energy_regen().unwrap_or(0.0)) as u64;
+        self.energy = max_energy.min(self.energy + regen);
+    }
+
+    // uses the first active skill whose rules are met, otherwise falls back to a basic attack
+    fn use_active(
+        &mut self, 
+        other: &mut Char<'a>, 
+        skills_map: &'a Registry, 
+        turn: u64, 
+        log: &mut CombatLog<'a>
+    ) -> TurnAction<'a> {
+        let control = self.get_control();
+        if !control.can_act() {
+            return TurnAction::Stunned;
+        }
+        if self.lose_action(log) {
+            return TurnAction::KnockedBack;
+        }
+        let (hp, max_hp, energy) = (self.hp, self.stat.get_hp().unwrap_or(0), self.energy);
+        // consumables come first, and can still be used while dazed
+        if let Some(item) = self.items.iter_mut().find(|i| i.should_use(turn, hp, max_hp, &self.skills)) {
+            item.consume();
+            let (name, consumable) = (item.get_name(), item.get_consumable());
+            log.push(Event::Consumable { target: self.name, item: name });
+            for id in consumable.get_cure() {
+                self.skills.remove(id);
+            }
+            if let Some(s) = consumable.get_effect() {
+                self.add_skill(&skills_map[s], skills_map, None);
+            }
+            return TurnAction::Consumable;
+        }
+        if !control.can_use_skills() {
+            return TurnAction::BasicAttack;
+        }
+        let active = match self.actives.iter_mut().find(|a| a.should_use(turn, hp, max_hp, energy, &self.skills)) {
+            Some(a) => a,
+            None => return TurnAction::BasicAttack,
+        };
+        active.trigger(turn);
+        let (cost, self_effect, other_effect) = (active.get_energy(), active.get_self_effect(), active.get_other_effect());
+        let action = active.get_action();
+
+        self.energy -= cost;
+        let caster = self.compute();
+        if let Some(s) = self_effect {
+            self.add_skill(&skills_map[s], skills_map, Some(&caster));
+        }
+        if let Some(s) = other_effect {
+            other.add_skill(&skills_map[s], skills_map, Some(&caster));
+        }
+        action
+    }
+
+    // each secondary effect is rolled in a fixed order against the current stat of the target
+    // then the passives triggered by the hit are applied
+    fn resolve_hit(
+        &mut self,
+        stat: &Stat, 
+        other: &mut Char<'a>, 
+        skills_map: &'a Registry, 
+        bodypart_hit :BodyPart, 
+        is_crit: bool, 
+        log: &mut CombatLog<'a>
+    ) {
+        for t in self.triggers.clone().iter().filter(|t| t.is_triggered(is_crit)) {
+            let skill = &skills_map[t.get_effect()];
+            match t.get_target() {
+                TriggerTarget::User => self.add_skill(skill, skills_map, Some(stat)),
+                TriggerTarget::Opponent => other.add_skill(skill, skills_map, Some(stat)),
+            };
+        }
+        let other_stat = other.compute();
+        for (roll, success) in stat.additional_effect(&other_stat, bodypart_hit, is_crit) {
+            if roll.probability() <= 0.0 {
+                continue;
+            }
+            let applied = if success { other.add_skill(&skills_map[roll.get_id().id()], skills_map, Some(stat)) } else { None };
+            log.push(Event::Effect { target: other.name, roll, success, applied });
+            if applied == Some(BuiltinEffect::Knockback.id()) {
+                other.collide(&skills_map[BuiltinEffect::Knockback.id()], skills_map, bodypart_hit, log);
             }
         }
     }
@@ -58,6 +282,8 @@ impl<'a> Char<'a> {
 struct ResultSimulation {
     first_hp_at_end: u64,
     second_hp_at_end: u64,
+    first_healed: u64,
+    second_healed: u64,
     turn: u64,
 }
 
@@ -68,6 +294,8 @@ impl Add for ResultSimulation {
         ResultSimulation {
             first_hp_at_end: self.first_hp_at_end + rhs.first_hp_at_end,
             second_hp_at_end: self.second_hp_at_end + rhs.second_hp_at_end,
+            first_healed: self.first_healed + rhs.first_healed,
+            second_healed: self.second_healed + rhs.second_healed,
             turn: self.turn + rhs.turn,
         }
     }
@@ -96,141 +324,191 @@ impl StatSimu {
 fn simulate_damage_cycle_attack_via_stat<'a>(
     first: &mut Char<'a>, 
     second: &mut Char<'a>, 
-    block_first: u64, 
-    block_second: u64, 
-    skills_map: &'a HashMap<IdSkills, Skill>
-) -> Option<[f64; 4]> 
+    first_action: TurnAction<'a>,
+    second_action: TurnAction<'a>,
+    skills_map: &'a Registry,
+    log: &mut CombatLog<'a>
+) 
 {
     let first_stat = &first.compute();
     let second_stat = &second.compute();
-    let hit_first: Hit = first_stat.attack(second_stat);
-    let hit_second: Hit = second_stat.attack(first_stat);
-
-    let first_hit_type: HitType = hit_first.draw(None);
-    let (first_dmg, second_dmg_block) = first_stat.get_damage(
-        second_stat, 
-        hit_first.get_bodypart_hit(), 
-        first_hit_type, 
-        block_second
-    );
-    match first_hit_type {
-        HitType::CritHit => first.resolve_hit(second, skills_map, hit_first.get_bodypart_hit(), true),
-        HitType::NormalHit => first.resolve_hit(second, skills_map, hit_first.get_bodypart_hit(), false),
-        HitType::BlockCritHit => first.resolve_hit(second, skills_map, hit_first.get_bodypart_hit(), true),
-        HitType::BlockNormalHit => first.resolve_hit(second, skills_map, hit_first.get_bodypart_hit(), false),
-        _ => (),
+    let hit_first: Hit = first_stat.attack(second_stat, first.ruleset.rules());
+    let hit_second: Hit = second_stat.attack(first_stat, second.ruleset.rules());
+
+    // a character spending its turn on a buff doesn't attack, and can't be countered
+    let first_strike = first_action.strike_stat(first_stat).map(|s| first.ruleset.resolve(&s));
+    let second_strike = second_action.strike_stat(second_stat).map(|s| second.ruleset.resolve(&s));
+
+    let first_dmg = match &first_strike {
+        Some(strike) => {
+            let hit_strike = strike.attack(second_stat, first.ruleset.rules());
+            let hit_type = hit_strike.draw(None);
+            let (dmg, dmg_block) = strike.get_damage(
+                second_stat, 
+                hit_strike.get_bodypart_hit(), 
+                hit_type, 
+                second.block,
+                first.ruleset.rules()
+            );
+            second.damage_block(dmg_block);
+            match hit_type {
+                HitType::CritHit => first.resolve_hit(strike, second, skills_map, hit_strike.get_bodypart_hit(), true, log),
+                HitType::NormalHit => first.resolve_hit(strike, second, skills_map, hit_strike.get_bodypart_hit(), false, log),
+                HitType::BlockCritHit => first.resolve_hit(strike, second, skills_map, hit_strike.get_bodypart_hit(), true, log),
+                HitType::BlockNormalHit => first.resolve_hit(strike, second, skills_map, hit_strike.get_bodypart_hit(), false, log),
+                _ => (),
+            };
+            dmg
+        },
+        None => 0.0,
     };
 
-    let second_counter_hit_type: HitType = hit_second.draw(second_stat.get_counter());
+    let second_counter_hit_type: HitType = if first_strike.is_some() && second_action.can_counter() { hit_second.draw(second_stat.get_counter()) } else { HitType::NoHit };
+    let second_counter_hit_type: HitType = if second_counter_hit_type != HitType::NoHit && second.lose_action(log) { HitType::NoHit } else { second_counter_hit_type };
     let (second_counter_dmg, first_counter_dmg_block) = second_stat.get_damage(
         first_stat, 
         hit_second.get_bodypart_hit(), 
         second_counter_hit_type, 
-        block_first
+        first.block,
+        second.ruleset.rules()
     );
+    first.damage_block(first_counter_dmg_block);
     match second_counter_hit_type {
-        HitType::CritHit => second.resolve_hit(first, skills_map, hit_second.get_bodypart_hit(), true),
-        HitType::NormalHit => second.resolve_hit(first, skills_map, hit_second.get_bodypart_hit(), false),
-        HitType::BlockCritHit => second.resolve_hit(first, skills_map, hit_second.get_bodypart_hit(), true),
-        HitType::BlockNormalHit => second.resolve_hit(first, skills_map, hit_second.get_bodypart_hit(), false),
+        HitType::CritHit => second.resolve_hit(second_stat, first, skills_map, hit_second.get_bodypart_hit(), true, log),
+        HitType::NormalHit => second.resolve_hit(second_stat, first, skills_map, hit_second.get_bodypart_hit(), false, log),
+        HitType::BlockCritHit => second.resolve_hit(second_stat, first, skills_map, hit_second.get_bodypart_hit(), true, log),
+        HitType::BlockNormalHit => second.resolve_hit(second_stat, first, skills_map, hit_second.get_bodypart_hit(), false, log),
         _ => (),
     };
 
-    let second_hit_type: HitType = hit_second.draw(None);
-    let (second_dmg, first_dmg_block) = second_stat.get_damage(
-        first_stat, 
-        hit_second.get_bodypart_hit(), 
-        second_hit_type, 
-        block_first - first_counter_dmg_block as u64
-    );
-    match second_hit_type {
-        HitType::CritHit => second.resolve_hit(first, skills_map, hit_second.get_bodypart_hit(), true),
-        HitType::NormalHit => second.resolve_hit(first, skills_map, hit_second.get_bodypart_hit(), false),
-        HitType::BlockCritHit => second.resolve_hit(first, skills_map, hit_second.get_bodypart_hit(), true),
-        HitType::BlockNormalHit => second.resolve_hit(first, skills_map, hit_second.get_bodypart_hit(), false),
-        _ => (),
+    let second_dmg = match &second_strike {
+        Some(strike) => {
+            let hit_strike = strike.attack(first_stat, second.ruleset.rules());
+            let hit_type = hit_strike.draw(None);
+            let (dmg, dmg_block) = strike.get_damage(
+                first_stat, 
+                hit_strike.get_bodypart_hit(), 
+                hit_type, 
+                first.block,
+                second.ruleset.rules()
+            );
+            first.damage_block(dmg_block);
+            match hit_type {
+                HitType::CritHit => second.resolve_hit(strike, first, skills_map, hit_strike.get_bodypart_hit(), true, log),
+                HitType::NormalHit => second.resolve_hit(strike, first, skills_map, hit_strike.get_bodypart_hit(), false, log),
+                HitType::BlockCritHit => second.resolve_hit(strike, first, skills_map, hit_strike.get_bodypart_hit(), true, log),
+                HitType::BlockNormalHit => second.resolve_hit(strike, first, skills_map, hit_strike.get_bodypart_hit(), false, log),
+                _ => (),
+            };
+            dmg
+        },
+        None => 0.0,
     };
 
-    let first_counter_hit_type: HitType = hit_first.draw(first_stat.get_counter());
+    let first_counter_hit_type: HitType = if second_strike.is_some() && first_action.can_counter() { hit_first.draw(first_stat.get_counter()) } else { HitType::NoHit };
+    let first_counter_hit_type: HitType = if first_counter_hit_type != HitType::NoHit && first.lose_action(log) { HitType::NoHit } else { first_counter_hit_type };
     let (first_counter_dmg, second_counter_dmg_block) = first_stat.get_damage(
         second_stat, 
         hit_first.get_bodypart_hit(), 
-        first_hit_type, 
-        block_second - second_dmg_block as u64
+        first_counter_hit_type, 
+        second.block,
+        first.ruleset.rules()
     );
+    second.damage_block(second_counter_dmg_block);
     match first_counter_hit_type {
-        HitType::CritHit => first.resolve_hit(second, skills_map, hit_first.get_bodypart_hit(), true),
-        HitType::NormalHit => first.resolve_hit(second, skills_map, hit_first.get_bodypart_hit(), false),
-        HitType::BlockCritHit => first.resolve_hit(second, skills_map, hit_first.get_bodypart_hit(), true),
-        HitType::BlockNormalHit => first.resolve_hit(second, skills_map, hit_first.get_bodypart_hit(), false),
+        HitType::CritHit => first.resolve_hit(first_stat, second, skills_map, hit_first.get_bodypart_hit(), true, log),
+        HitType::NormalHit => first.resolve_hit(first_stat, second, skills_map, hit_first.get_bodypart_hit(), false, log),
+        HitType::BlockCritHit => first.resolve_hit(first_stat, second, skills_map, hit_first.get_bodypart_hit(), true, log),
+        HitType::BlockNormalHit => first.resolve_hit(first_stat, second, skills_map, hit_first.get_bodypart_hit(), false, log),
         _ => (),
     };
     
-    Some([
-        first_dmg + first_counter_dmg + second_stat.residual_damage(), 
-        second_dmg + second_counter_dmg + first_stat.residual_damage(),
-        first_dmg_block + first_counter_dmg_block,
-        second_dmg_block + second_counter_dmg_block,
-    ])
+    log.add_damage(second.name, DamageSource::Attack, first_dmg as u64);
+    log.add_damage(second.name, DamageSource::Counter, first_counter_dmg as u64);
+    log.add_damage(first.name, DamageSource::Attack, second_dmg as u64);
+    log.add_damage(first.name, DamageSource::Counter, second_counter_dmg as u64);
+    let (first_hp, second_hp) = (first.hp, second.hp);
+    second.take_damage(first_dmg + first_counter_dmg);
+    first.take_damage(second_dmg + second_counter_dmg);
+
+    // the attack drains life with the stat of its strike, then the counter with the stat of the character,
+    // from the hp they actually removed; a dead character doesn't drain life anymore
+    if first.hp > 0 {
+        let removed = (second_hp - second.hp) as f64;
+        let drained = first_strike.as_ref().unwrap_or(first_stat).drain(first_dmg, removed)
+            + first_stat.drain(first_counter_dmg, (removed - first_dmg).max(0.0));
+        first.heal(drained, HealSource::Lifesteal, log);
+    }
+    if second.hp > 0 {
+        let removed = (first_hp - first.hp) as f64;
+        let drained = second_strike.as_ref().unwrap_or(second_stat).drain(second_dmg, removed)
+            + second_stat.drain(second_counter_dmg, (removed - second_dmg).max(0.0));
+        second.heal(drained, HealSource::Lifesteal, log);
+    }
 }
 
 fn simulate_damage_n_cycles<'a>(
     first :& mut Char<'a>, 
     second:& mut Char<'a>, 
     n :u64,
-    skills_map: &'a HashMap<IdSkills, Skill>
+    skills_map: &'a Registry,
+    log: &mut CombatLog<'a>
 ) -> Option<ResultSimulation> 
 {
-    let mut hp_first = first.stat.get_hp()?;
-    let mut hp_second = second.stat.get_hp()?;
-    let mut block_first = first.stat.get_block()?;
-    let mut block_second = second.stat.get_block()?;
     let mut count: u64 = 0;
     for _ in 0..n {
-        let [
-            damage_first, damage_second, 
-            damage_block_first, damage_block_second
-        ] = simulate_damage_cycle_attack_via_stat(
-            first, second, block_first, block_second, skills_map
-        )?;
-        hp_first = if damage_second as u64 > hp_first { 0 } else { hp_first - damage_second as u64 };
-        hp_second = if damage_first as u64 > hp_second { 0 } else { hp_second - damage_first as u64 };
-        block_first = if damage_block_second as u64 > block_first { 0 } else { block_first - damage_block_second as u64 };
-        block_second = if damage_block_first as u64 > block_second { 0 } else { block_second - damage_block_first as u64 };
+        log.set_turn(count + 1);
+        let first_action = first.use_active(second, skills_map, count + 1, log);
+        let second_action = second.use_active(first, skills_map, count + 1, log);
+        simulate_damage_cycle_attack_via_stat(
+            first, second, first_action, second_action, skills_map, log
+        );
+        first.tick_dots(log);
+        second.tick_dots(log);
         
         count += 1;
-        first.remove_outdated_skills(&count);
-        second.remove_outdated_skills(&count);
+        first.tick_skills();
+        second.tick_skills();
 
-        if hp_first == 0 || hp_second == 0 {
+        if first.hp == 0 || second.hp == 0 {
             break;
         }
 
-        block_first += (block_first as f64 * first.stat.get_block_recovery().unwrap_or(0.0)) as u64;
-        if block_first > first.stat.get_block()? { block_first = first.stat.get_block()?}
-        block_second += (block_second as f64 * second.stat.get_block_recovery().unwrap_or(0.0)) as u64;
-        if block_second > second.stat.get_block()? { block_second = second.stat.get_block()?}
+        first.recover_block();
+        second.recover_block();
+
+        first.regen_energy();
+        second.regen_energy();
+
+        first.regen_hp(log);
+        second.regen_hp(log);
     }
 
     Some(ResultSimulation { 
-        first_hp_at_end: hp_first,
-        second_hp_at_end: hp_second,
+        first_hp_at_end: first.hp,
+        second_hp_at_end: second.hp,
+        first_healed: first.healed,
+        second_healed: second.healed,
         turn: count, }
     )
 }
 
 fn monte_carlo_damage<'a>(
-    first_data: &Char, 
-    second_data: &Char, 
+    first_data: &Char<'a>, 
+    second_data: &Char<'a>, 
     n: u64,
-    skills_map: &'a HashMap<IdSkills, Skill>
-) -> Option<[StatSimu; 3]> 
+    skills_map: &'a Registry
+) -> Option<[StatSimu; 5]> 
 {
     let mut sum_win: u64 = 0;
     let mut sum_hp_first: u64 = 0;
     let mut sum_hp_second: u64 = 0;
     let mut sumsq_hp_first: u64 = 0;
     let mut sumsq_hp_second: u64 = 0;
+    let mut sum_healed_first: u64 = 0;
+    let mut sum_healed_second: u64 = 0;
+    let mut sumsq_healed_first: u64 = 0;
+    let mut sumsq_healed_second: u64 = 0;
     let n_simu: u64 = 10000;
 
     for _ in 0..n_simu {
@@ -240,12 +518,17 @@ fn monte_carlo_damage<'a>(
             &mut first, 
             &mut second, 
             n,
-            skills_map)?;
+            skills_map,
+            &mut CombatLog::new(false))?;
         sum_win += if result_simulation.first_hp_at_end > 0 {1} else {0};
         sum_hp_first += result_simulation.first_hp_at_end;
         sumsq_hp_first += result_simulation.first_hp_at_end * result_simulation.first_hp_at_end;
         sum_hp_second += result_simulation.second_hp_at_end;
         sumsq_hp_second += result_simulation.second_hp_at_end* result_simulation.second_hp_at_end;
+        sum_healed_first += result_simulation.first_healed;
+        sumsq_healed_first += result_simulation.first_healed * result_simulation.first_healed;
+        sum_healed_second += result_simulation.second_healed;
+        sumsq_healed_second += result_simulation.second_healed * result_simulation.second_healed;
     }
 
     let n_first = sum_win;
@@ -254,6 +537,8 @@ fn monte_carlo_damage<'a>(
     let mean_win: f64 = sum_win as f64/ n_simu as f64;
     let mean_hp_first: f64 = if n_first != 0 { sum_hp_first as f64/ n_first as f64 } else { 0.0 };
     let mean_hp_second: f64 = if n_second != 0 { sum_hp_second as f64/ n_second as f64 } else { 0.0 };
+    let mean_healed_first: f64 = sum_healed_first as f64 / n_simu as f64;
+    let mean_healed_second: f64 = sum_healed_second as f64 / n_simu as f64;
     Some([
         StatSimu{
             mean: mean_win,
@@ -269,49 +554,255 @@ fn monte_carlo_damage<'a>(
             mean: mean_hp_second,
             var: if n_second != 0 { sumsq_hp_second as f64/ n_second as f64 - mean_hp_second * mean_hp_second } else { 0.0 },
             n: n_second,
+        },
+        StatSimu{
+            mean: mean_healed_first,
+            var: sumsq_healed_first as f64 / n_simu as f64 - mean_healed_first * mean_healed_first,
+            n: n_simu,
+        },
+        StatSimu{
+            mean: mean_healed_second,
+            var: sumsq_healed_second as f64 / n_simu as f64 - mean_healed_second * mean_healed_second,
+            n: n_simu,
         }])
 }
 
-fn main() -> Result<(), serde_yaml::Error> {
-    let path_chars: &str = "./data/characters.yaml";
-    let path_effects: &str = "./data/effects.yaml";
-    let path_action: &str = "./data/action.yaml";
+// every data file the simulation relies on
+struct Data {
+    chars: HashMap<String, Character>,
+    effects: Registry,
+    action: HashMap<String, Action>,
+    skills: HashMap<String, ActiveSkill>,
+    consumables: HashMap<String, Consumable>,
+    items: HashMap<String, Equipment>,
+    attributes: HashMap<Attribute, Stat>,
+    passives: Catalog,
+    rulesets: HashMap<String, Ruleset>,
+    // game version characters.yaml, effects.yaml and rulesets.yaml come from
+    version: GameVersion,
+}
 
-    let file_chars = File::open(path_chars).expect("Unable to open file");
-    let file_effects = File::open(path_effects).expect("Unable to open file");
-    let file_action = File::open(path_action).expect("Unable to open file");
+// a yaml data file, its path prefixing any parsing error
+fn read<T: DeserializeOwned>(path: &str) -> Result<T, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+    serde_yaml::from_reader(&file).map_err(|e| format!("{}: {}", path, e))
+}
 
-    let deserialized_chars: HashMap<String, Stat> = serde_yaml::from_reader(&file_chars).unwrap();
-    let deserialized_effects: HashMap<IdSkills, Skill> = serde_yaml::from_reader(&file_effects).unwrap();
-    let deserialized_action: HashMap<String, Vec<IdSkills>> = serde_yaml::from_reader(&file_action).unwrap();
+impl Data {
+    // every data file but advisor.yaml is read from the archive of the given game version,
+    // the top-level files holding the latest version and the older ones being saved under data/<version>
+    fn load(version: Option<&GameVersion>) -> Result<Self, String> {
+        let dir = match version {
+            Some(v) => {
+                let (latest, _) = version::untag::<Mapping>("./data/characters.yaml", read("./data/characters.yaml")?)?;
+                if *v == latest { String::from("./data") } else { format!("./data/{}", v) }
+            },
+            None => String::from("./data"),
+        };
+        if let Some(v) = version {
+            if !std::path::Path::new(&dir).is_dir() {
+                return Err(format!("Unknown game version: {}", v));
+            }
+        }
+        let path = |file: &str| format!("{}/{}", dir, file);
+        let (chars_path, effects_path, rulesets_path) = (path("characters.yaml"), path("effects.yaml"), path("rulesets.yaml"));
+        let (chars_version, chars) = version::untag(&chars_path, read(&chars_path)?)?;
+        let (effects_version, effects) = version::untag(&effects_path, read(&effects_path)?)?;
+        let (rulesets_version, rulesets) = version::untag(&rulesets_path, read(&rulesets_path)?)?;
+        if let Some(v) = version {
+            if *v != chars_version {
+                return Err(format!("{} is tagged {} instead of {}", chars_path, chars_version, v));
+            }
+        }
+        let warnings = version::check(&[
+            (&chars_path, &chars_version), (&effects_path, &effects_version), (&rulesets_path, &rulesets_version)
+        ])?;
+        for w in warnings.iter() {
+            eprintln!("Warning: {}", w);
+        }
 
-    let ennemy_name = "crawler";
-    let mut ennemy: Char = Char { 
-        stat: deserialized_chars[ennemy_name].clone(), skills: HashMap::<IdSkills, &Skill>::new(), 
+        let data = Data {
+            chars: profile::resolve(&chars)?,
+            effects,
+            action: read(&path("action.yaml"))?,
+            skills: read(&path("skills.yaml"))?,
+            consumables: read(&path("consumables.yaml"))?,
+            items: read(&path("items.yaml"))?,
+            attributes: read(&path("attributes.yaml"))?,
+            passives: read(&path("passives.yaml"))?,
+            rulesets,
+            version: chars_version,
+        };
+        data.validate()?;
+        Ok(data)
+    }
+
+    // every effect referenced by the other data files must be registered in effects.yaml
+    fn validate(&self) -> Result<(), String> {
+        self.effects.validate()?;
+        self.passives.validate()?;
+        let referenced = self.action.values().flat_map(|a| a.get_effects())
+            .chain(self.skills.values().flat_map(|s| s.get_effects()))
+            .chain(self.consumables.values().flat_map(|c| c.get_effects()))
+            .chain(self.passives.get_effects());
+        for id in referenced {
+            self.effects.get(id)?;
+        }
+        for action in self.action.values() {
+            for p in action.get_active().iter() {
+                self.skills.get(p.get_skill()).ok_or(format!("Unknown skill: {}", p.get_skill()))?;
+            }
+            for p in action.get_consumables().iter() {
+                self.consumables.get(p.get_item()).ok_or(format!("Unknown consumable: {}", p.get_item()))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn character(&self, name: &str) -> Result<&Character, String> {
+        self.chars.get(name).ok_or(format!("Unknown character: {}", name))
+    }
+
+    fn ruleset(&self, name: &str) -> Result<&Ruleset, String> {
+        self.rulesets.get(name).ok_or(format!("Unknown ruleset: {}", name))
+    }
+
+    // a fighter ready for the combat under the given ruleset: its stat weakened by the passives of its opponent,
+    // and the effects, active skills and consumables of its side ("self" or "other") in action.yaml
+    fn fighter<'a>(
+        &'a self, name: &'a str, character: &Character, opponent: &Character, side: &str, ruleset: &'a Ruleset
+    ) -> Result<Char<'a>, String> {
+        let stat = character.build(&self.items, &self.attributes, &self.passives)
+            .map_err(|e| format!("{}: {}", name, e))?
+            .with_bonus(self.passives.other_effect(opponent.get_passives())?);
+        let mut fighter = Char::new(name, stat, ruleset);
+        fighter.triggers = self.passives.triggers(character.get_passives())?;
+
+        let action = self.action.get(side).ok_or(format!("Unknown side in action.yaml: {}", side))?;
+        for s in action.get_start().iter() {
+            fighter.add_skill(self.effects.get(s)?, &self.effects, None);
+        }
+        for p in action.get_active().iter() {
+            let skill = self.skills.get(p.get_skill()).ok_or(format!("Unknown skill: {}", p.get_skill()))?;
+            fighter.actives.push(Active::new(skill, p));
+        }
+        for p in action.get_consumables().iter() {
+            let item = self.consumables.get(p.get_item()).ok_or(format!("Unknown consumable: {}", p.get_item()))?;
+            fighter.items.push(Item::new(item, p));
+        }
+        Ok(fighter)
+    }
+}
+
+const DEFAULT_RULESET: &str = "classic";
+
+// the combat of the player against the foe simulated under each ruleset with the same random numbers,
+// one row per ruleset
+fn compare(data: &Data, names: &[String], ennemy_name: &str, max_turn: u64, seed: u64) -> Result<String, String> {
+    let (ennemy_char, player_char) = (data.character(ennemy_name)?, data.character("main")?);
+    let mut table = format!("{:<24}{:<12}{:<16}{:<16}\n", "ruleset", "foe win", "foe hp left", "player hp left");
+    for name in names.iter() {
+        let ruleset = data.ruleset(name)?;
+        let ennemy = data.fighter(ennemy_name, ennemy_char, player_char, "other", ruleset)?;
+        let player = data.fighter("player", player_char, ennemy_char, "self", ruleset)?;
+        random::seed(seed);
+        let result = monte_carlo_damage(&ennemy, &player, max_turn, &data.effects)
+            .ok_or(format!("{}: the combat could not be simulated", name))?;
+        table += &format!(
+            "{:<24}{:<12}{:<16}{:<16}\n",
+            name, format!("{:.1}%", result[0].mean * 100.0), format!("{:.1}", result[1].mean), format!("{:.1}", result[2].mean)
+        );
+    }
+    Ok(table)
+}
+
+fn main() -> Result<(), String> {
+    let args: Vec<String> = std::env::args().collect();
+    let version = match args.iter().position(|a| a == "--game-version") {
+        Some(i) => Some(GameVersion::parse(args.get(i + 1).ok_or("Missing version after --game-version")?)?),
+        None => None,
     };
-    let mut player: Char = Char { 
-        stat: deserialized_chars["main"].clone(), skills: HashMap::<IdSkills, &Skill>::new(), 
+    let data = Data::load(version.as_ref())?;
+    // drawn at random unless given, and printed with the results so that they can be reproduced
+    let seed = match args.iter().position(|a| a == "--seed") {
+        Some(i) => {
+            let seed = args.get(i + 1).ok_or("Missing seed after --seed")?;
+            seed.parse::<u64>().map_err(|_| format!("Invalid seed: {}", seed))?
+        },
+        None => random::draw_seed(),
     };
 
-    for s in deserialized_action["other"].iter() {
-        ennemy.skills.insert(deserialized_effects[s].id, &deserialized_effects[s]);
+    let ruleset_name = match args.iter().position(|a| a == "--ruleset") {
+        Some(i) => args.get(i + 1).ok_or("Missing ruleset name after --ruleset")?.as_str(),
+        None => DEFAULT_RULESET,
+    };
+    let ruleset = data.ruleset(ruleset_name)?;
+
+    if args.iter().any(|a| a == "advise") {
+        let mut advisor: Advisor = read("./data/advisor.yaml")?;
+        advisor.validate()?;
+        // the seed of advisor.yaml unless one is given
+        if args.iter().any(|a| a == "--seed") {
+            advisor.set_seed(seed);
+        }
+        // the game version, the ruleset and the seed the results come from, to reproduce them later on
+        println!("Game version {} with the {} ruleset, seed {}\n", data.version, ruleset_name, advisor.get_seed());
+        println!("{}", advisor.rank(&data, ruleset)?);
+        return Ok(());
     }
-    for s in deserialized_action["self"].iter() {
-        player.skills.insert(deserialized_effects[s].id, &deserialized_effects[s]);
+
+    if let Some(i) = args.iter().position(|a| a == "show") {
+        let name = args.get(i + 1).ok_or("Missing profile name after show")?;
+        let character = data.chars.get(name).ok_or(format!("Unknown profile: {}", name))?;
+        let stat = character.build(&data.items, &data.attributes, &data.passives)?;
+        // the profile once its parents applied, then the stat it fights with
+        println!("{}", profile::show(name, character));
+        println!("{}", profile::show("combat_stat", &ruleset.resolve(&stat)));
+        return Ok(());
     }
 
+    let ennemy_name = "crawler";
     let max_turn: u64 = 100;
+    if let Some(i) = args.iter().position(|a| a == "compare") {
+        println!("Game version {}, seed {}\n", data.version, seed);
+        print!("{}", compare(&data, &args[i + 1..], ennemy_name, max_turn, seed)?);
+        return Ok(());
+    }
+
+    let (ennemy_char, player_char) = (data.character(ennemy_name)?, data.character("main")?);
+    let ennemy = data.fighter(ennemy_name, ennemy_char, player_char, "other", ruleset)?;
+    let player = data.fighter("player", player_char, ennemy_char, "self", ruleset)?;
+
+    // the sample combat of the log is the first combat of the simulation
+    if args.iter().any(|a| a == "--log") {
+        random::seed(seed);
+        let mut log = CombatLog::new(true);
+        simulate_damage_n_cycles(
+            &mut ennemy.clone(), 
+            &mut player.clone(), 
+            max_turn, 
+            &data.effects, 
+            &mut log
+        );
+        println!("{}", log);
+    }
+
+    random::seed(seed);
     let raw_expectation = monte_carlo_damage(
-        &mut ennemy, 
-        &mut player, 
+        &ennemy, 
+        &player, 
         max_turn, 
-        &deserialized_effects
+        &data.effects
     );
-    let unwrap_raw = raw_expectation.unwrap();
+    let unwrap_raw = raw_expectation.ok_or("The combat could not be simulated")?;
+
+    println!("Game version {} with the {} ruleset, seed {}\n", data.version, ruleset_name, seed);
 
     let win_confidence_interval = unwrap_raw[0].confident_interval();
     let hp_ennemy = unwrap_raw[1].confident_interval();
     let hp_player = unwrap_raw[2].confident_interval();
+    let healed_ennemy = unwrap_raw[3].confident_interval();
+    let healed_player = unwrap_raw[4].confident_interval();
     println!(
         "Chance for {:} to win: {:}.\nConfidence interval with confidence level at 95%: [{:}, {:}]\n", 
         ennemy_name, win_confidence_interval[1], win_confidence_interval[0], win_confidence_interval[2]
@@ -324,6 +815,75 @@ fn main() -> Result<(), serde_yaml::Error> {
         "Expected player's hp at the end of combat if winning: {:}.\nConfidence interval with confidence level at 95%: [{:}, {:}]\n", 
         hp_player[1], hp_player[0], hp_player[2]
     );
+    println!(
+        "Expected {:}'s healing during the combat: {:}.\nConfidence interval with confidence level at 95%: [{:}, {:}]\n", 
+        ennemy_name, healed_ennemy[1], healed_ennemy[0], healed_ennemy[2]
+    );
+    println!(
+        "Expected player's healing during the combat: {:}.\nConfidence interval with confidence level at 95%: [{:}, {:}]\n", 
+        healed_player[1], healed_player[0], healed_player[2]
+    );
 
     Ok(()) 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_references() {
+        // a name missing from the data is reported instead of panicking
+        let mut data = Data::load(None).unwrap();
+        assert_eq!(data.character("nobody").err(), Some("Unknown character: nobody".to_string()));
+
+        let ruleset = Ruleset::default();
+        let (ennemy_char, player_char) = (data.character("crawler").unwrap(), data.character("main").unwrap());
+        assert!(data.fighter("player", player_char, ennemy_char, "self", &ruleset).is_ok());
+        assert_eq!(
+            data.fighter("player", player_char, ennemy_char, "nowhere", &ruleset).err(),
+            Some("Unknown side in action.yaml: nowhere".to_string())
+        );
+
+        data.skills.remove("Warcry");
+        data.consumables.remove("Bandage");
+        assert_eq!(data.validate(), Err("Unknown skill: Warcry".to_string()));
+        let (ennemy_char, player_char) = (&data.chars["crawler"], &data.chars["main"]);
+        assert_eq!(
+            data.fighter("player", player_char, ennemy_char, "self", &ruleset).err(),
+            Some("Unknown skill: Warcry".to_string())
+        );
+        data.skills = Data::load(None).unwrap().skills;
+        assert_eq!(data.validate(), Err("Unknown consumable: Bandage".to_string()));
+    }
+
+    #[test]
+    fn test_load_version() {
+        // the version the top-level files are tagged with is found in ./data
+        let version = |v: &str| GameVersion::parse(v).unwrap();
+        let data = Data::load(Some(&version("0.8.2.10"))).unwrap();
+        assert_eq!(data.version, version("0.8.2.10"));
+        assert_eq!(Data::load(Some(&version("0.1.0.0"))).err(), Some("Unknown game version: 0.1.0.0".to_string()));
+    }
+
+    #[test]
+    fn test_load_archive() {
+        // every file of an older version is read from its archive, found whatever the way the version is written
+        let data = Data::load(Some(&GameVersion::parse("0.8.2.09").unwrap())).unwrap();
+        assert_eq!(data.version, GameVersion::parse("0.8.2.9").unwrap());
+        assert!(data.items.is_empty() && data.skills.is_empty());
+        let (ennemy_char, player_char) = (data.character("crawler").unwrap(), data.character("main").unwrap());
+        let ruleset = data.ruleset("classic").unwrap();
+        let player = data.fighter("player", player_char, ennemy_char, "self", ruleset).unwrap();
+        let ennemy = data.fighter("crawler", ennemy_char, player_char, "other", ruleset).unwrap();
+        assert_eq!(player.hp, 76);
+        // the same seed replays the same combat
+        let combat = |seed| {
+            random::seed(seed);
+            let mut log = CombatLog::new(true);
+            simulate_damage_n_cycles(&mut ennemy.clone(), &mut player.clone(), 100, &data.effects, &mut log);
+            log.to_string()
+        };
+        assert_eq!(combat(42), combat(42));
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::{HashMap, HashSet};
+use serde::{Serialize, Deserialize};
+use crate::stat::Stat;
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Eq, Hash, Clone, Copy)]
+pub enum Tree {
+    Swords,
+    Axes,
+    Maces,
+    Daggers,
+    Greatswords,
+    Greataxes,
+    Greatmaces,
+    Spears,
+    Staves,
+    Bows,
+    Crossbows,
+    Shields,
+    DualWielding,
+    Athletics,
+    Warfare,
+    ArmoredCombat,
+    Pyromancy,
+    Geomancy,
+    Electromancy,
+    Arcanistics,
+    Astromancy,
+    Chronomancy,
+    Psimancy,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub enum TriggerEvent {
+    // any hit landing, critical or not
+    Hit,
+    Crit,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub enum TriggerTarget {
+    User,
+    Opponent,
+}
+
+// an effect from effects.yaml applied when the character lands a hit
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Trigger {
+    on: TriggerEvent,
+    target: TriggerTarget,
+    effect: String,
+}
+
+impl Trigger {
+    pub fn is_triggered(&self, is_crit: bool) -> bool {
+        match self.on {
+            TriggerEvent::Hit => true,
+            TriggerEvent::Crit => is_crit,
+        }
+    }
+
+    pub fn get_target(&self) -> TriggerTarget {
+        self.target
+    }
+
+    pub fn get_effect(&self) -> &str {
+        &self.effect
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct Passive {
+    self_effect: Option<Stat>,
+    other_effect: Option<Stat>,
+    #[serde(default)]
+    triggers: Vec<Trigger>,
+}
+
+// passives of each ability tree, found by their name
+#[derive(Serialize, Deserialize, PartialEq, Debug, Default)]
+#[serde(transparent)]
+pub struct Catalog {
+    trees: HashMap<Tree, HashMap<String, Passive>>,
+}
+
+impl Catalog {
+    // a passive is found by its name alone, which must then be unique across the trees
+    pub fn validate(&self) -> Result<(), String> {
+        let mut names = HashSet::new();
+        for name in self.trees.values().flat_map(|t| t.keys()) {
+            if !names.insert(name) {
+                return Err(format!("Passive in several trees: {}", name));
+            }
+        }
+        Ok(())
+    }
+
+    fn get(&self, name: &str) -> Result<&Passive, String> {
+        self.trees.values()
+            .find_map(|t| t.get(name))
+            .ok_or(format!("Unknown passive: {}", name))
+    }
+
+    // stat given to the character by its passives
+    pub fn self_effect(&self, passives: &[String]) -> Result<Stat, String> {
+        let mut stat = Stat::default();
+        for name in passives.iter() {
+            if let Some(e) = &self.get(name)?.self_effect {
+                stat += e.clone();
+            }
+        }
+        Ok(stat)
+    }
+
+    // stat given to the opponent of the character by its passives
+    pub fn other_effect(&self, passives: &[String]) -> Result<Stat, String> {
+        let mut stat = Stat::default();
+        for name in passives.iter() {
+            if let Some(e) = &self.get(name)?.other_effect {
+                stat += e.clone();
+            }
+        }
+        Ok(stat)
+    }
+
+    pub fn triggers(&self, passives: &[String]) -> Result<Vec<Trigger>, String> {
+        let mut triggers = Vec::new();
+        for name in passives.iter() {
+            triggers.extend(self.get(name)?.triggers.iter().cloned());
+        }
+        Ok(triggers)
+    }
+
+    // effects triggered by any passive of the catalog, to be found in effects.yaml
+    pub fn get_effects(&self) -> Vec<&str> {
+        self.trees.values()
+            .flat_map(|t| t.values())
+            .flat_map(|p| p.triggers.iter())
+            .map(|t| t.get_effect())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalog() -> Catalog {
+        serde_yaml::from_str(
+            "Athletics:\n  Disengage:\n    self_effect:\n      dodge: 0.03\n    other_effect:\n      accuracy: -0.05\n\
+            Warfare:\n  Rage:\n    self_effect:\n      crit_chance: 0.05\n    triggers:\n      - on: Crit\n        target: User\n        effect: WarcrySelf\n"
+        ).unwrap()
+    }
+
+    #[test]
+    fn test_effects() {
+        let passives = vec![String::from("Disengage"), String::from("Rage")];
+        let self_effect: Stat = serde_yaml::from_str("dodge: 0.03\ncrit_chance: 0.05\n").unwrap();
+        let other_effect: Stat = serde_yaml::from_str("accuracy: -0.05\n").unwrap();
+        assert_eq!(catalog().self_effect(&passives), Ok(self_effect));
+        assert_eq!(catalog().other_effect(&passives), Ok(other_effect));
+    }
+
+    #[test]
+    fn test_triggers() {
+        let triggers = catalog().triggers(&[String::from("Rage"), String::from("Disengage")]).unwrap();
+        assert_eq!(triggers.len(), 1);
+        assert!(triggers[0].is_triggered(true) && !triggers[0].is_triggered(false));
+        assert_eq!((triggers[0].get_target(), triggers[0].get_effect()), (TriggerTarget::User, "WarcrySelf"));
+        assert_eq!(catalog().get_effects(), vec!["WarcrySelf"]);
+    }
+
+    #[test]
+    fn test_unknown_passive() {
+        assert_eq!(catalog().self_effect(&[String::from("Cleave")]), Err(String::from("Unknown passive: Cleave")));
+    }
+
+    #[test]
+    fn test_duplicate_passive() {
+        assert_eq!(catalog().validate(), Ok(()));
+        let catalog: Catalog = serde_yaml::from_str(
+            "Athletics:\n  Disengage:\n    self_effect:\n      dodge: 0.03\n\
+            Warfare:\n  Disengage:\n    self_effect:\n      dodge: 0.05\n"
+        ).unwrap();
+        assert_eq!(catalog.validate(), Err(String::from("Passive in several trees: Disengage")));
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use serde::Serialize;
+use serde_yaml::{Mapping, Value};
+use crate::equipment::Character;
+
+const BODY_PARTS: [&str; 4] = ["head", "torso", "hands", "legs"];
+
+// the fields of the child override the ones of the parent, nested mappings (body parts, attributes)
+// being merged field by field and lists (damage, passives, equipment) replaced as a whole
+fn merge(parent: Value, child: Value) -> Value {
+    match (parent, child) {
+        (Value::Mapping(mut parent), Value::Mapping(child)) => {
+            for (key, value) in child {
+                match parent.get_mut(&key) {
+                    Some(p) => *p = merge(std::mem::take(p), value),
+                    None => {
+                        parent.insert(key, value);
+                    },
+                }
+            }
+            Value::Mapping(parent)
+        },
+        (_, child) => child,
+    }
+}
+
+// armor given once applies to every body part, the fields given for a body part overriding it
+fn expand_armor(mut profile: Mapping) -> Mapping {
+    if let Some(armor) = profile.remove("armor") {
+        for part in BODY_PARTS {
+            let own = profile.remove(part).unwrap_or(Value::Mapping(Mapping::new()));
+            profile.insert(Value::from(part), merge(armor.clone(), own));
+        }
+    }
+    profile
+}
+
+// chain holds the profiles being resolved, to detect a profile extending itself
+fn resolve_profile(name: &str, profiles: &Mapping, chain: &mut Vec<String>) -> Result<Value, String> {
+    if chain.iter().any(|n| n == name) {
+        return Err(format!("Cyclic extends: {} -> {}", chain.join(" -> "), name));
+    }
+    let mut profile = match profiles.get(name) {
+        Some(Value::Mapping(m)) => expand_armor(m.clone()),
+        Some(_) => return Err(format!("Invalid profile: {}", name)),
+        None => return Err(format!("Unknown profile: {}", name)),
+    };
+    let resolved = match profile.remove("extends") {
+        Some(Value::String(parent)) => {
+            chain.push(String::from(name));
+            let parent = resolve_profile(&parent, profiles, chain)?;
+            chain.pop();
+            merge(parent, Value::Mapping(profile))
+        },
+        Some(_) => return Err(format!("{}: extends expects the name of a profile", name)),
+        None => Value::Mapping(profile),
+    };
+    Ok(resolved)
+}
+
+// every profile of characters.yaml with its parents applied
+pub fn resolve(profiles: &Mapping) -> Result<HashMap<String, Character>, String> {
+    let mut characters = HashMap::new();
+    for key in profiles.keys() {
+        let name = key.as_str().ok_or(format!("Invalid profile name: {:?}", key))?;
+        let profile = resolve_profile(name, profiles, &mut Vec::new())?;
+        let character = serde_yaml::from_value(profile).map_err(|e| format!("{}: {}", name, e))?;
+        characters.insert(String::from(name), character);
+    }
+    Ok(characters)
+}
+
+fn prune(value: Value) -> Value {
+    match value {
+        Value::Mapping(m) => Value::Mapping(
+            m.into_iter().filter(|(_, v)| !v.is_null()).map(|(k, v)| (k, prune(v))).collect()
+        ),
+        other => other,
+    }
+}
+
+// yaml of a profile or a stat under the given key, without the fields left unset
+pub fn show<T: Serialize>(key: &str, value: &T) -> String {
+    serde_yaml::to_value(value)
+        .and_then(|v| serde_yaml::to_string(&Mapping::from_iter([(Value::from(key), prune(v))])))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stat::Stat;
+
+    fn profiles(yaml: &str) -> Mapping {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn stat(yaml: &str) -> Stat {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_extends() {
+        let characters = resolve(&profiles(
+            "base:\n  hp: 100\n  dodge: 0.5\n  head:\n    protection: 5\n    phy_res: 0.1\n  equipment: [Sword, Helmet]\n\
+            rot:\n  extends: base\n  dodge: 0.25\n  head:\n    protection: 7\n  equipment: [Sword]\n\
+            late:\n  extends: rot\n  hp: 120\n"
+        )).unwrap();
+        let expected: Character = serde_yaml::from_str(
+            "hp: 120\ndodge: 0.25\nhead:\n  protection: 7\n  phy_res: 0.1\nequipment: [Sword]\n"
+        ).unwrap();
+        assert_eq!(characters["late"], expected);
+        assert_eq!(characters.len(), 3);
+    }
+
+    #[test]
+    fn test_armor() {
+        let characters = resolve(&profiles(
+            "bear:\n  armor:\n    protection: 2\n    bleed_res: 0.2\n  head:\n    protection: 0\n"
+        )).unwrap();
+        let part = "protection: 2\n  bleed_res: 0.2\n";
+        let expected: Character = serde_yaml::from_str(&format!(
+            "head:\n  protection: 0\n  bleed_res: 0.2\ntorso:\n  {}hands:\n  {}legs:\n  {}", part, part, part
+        )).unwrap();
+        assert_eq!(characters["bear"], expected);
+    }
+
+    #[test]
+    fn test_child_armor() {
+        let characters = resolve(&profiles(
+            "base:\n  torso:\n    protection: 5\n    phy_res: 0.1\n\
+            armored:\n  extends: base\n  armor:\n    protection: 8\n"
+        )).unwrap();
+        let expected = stat("head:\n  protection: 8\ntorso:\n  protection: 8\n  phy_res: 0.1\nhands:\n  protection: 8\nlegs:\n  protection: 8\n");
+        let built = characters["armored"].build(&HashMap::new(), &HashMap::new(), &Default::default());
+        assert_eq!(built, Ok(expected));
+    }
+
+    #[test]
+    fn test_invalid_extends() {
+        assert_eq!(
+            resolve(&profiles("rot:\n  extends: base\n")).map(|c| c.len()),
+            Err(String::from("Unknown profile: base"))
+        );
+        assert_eq!(
+            resolve(&profiles("a:\n  extends: b\nb:\n  extends: a\n")).map(|c| c.len()),
+            Err(String::from("Cyclic extends: a -> b -> a"))
+        );
+    }
+
+    #[test]
+    fn test_show() {
+        assert_eq!(show("bear", &stat("hp: 100\nhead:\n  protection: 5\n")), "bear:\n  hp: 100\n  head:\n    protection: 5\n");
+    }
+}
@@ -0,0 +1,37 @@
+use std::cell::RefCell;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+// every random draw of the combat goes through this generator, so that a simulation can be replayed
+// with the same random numbers by seeding it (common random numbers)
+thread_local! {
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+pub fn seed(seed: u64) {
+    RNG.with(|r| *r.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
+// a seed drawn from the entropy of the system, for a simulation that can still be replayed once it's printed
+pub fn draw_seed() -> u64 {
+    StdRng::from_entropy().gen()
+}
+
+// uniform draw in [0, 1)
+pub fn uniform() -> f64 {
+    RNG.with(|r| r.borrow_mut().gen_range(0.0..1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seed() {
+        seed(42);
+        let first: Vec<f64> = (0..10).map(|_| uniform()).collect();
+        seed(42);
+        let second: Vec<f64> = (0..10).map(|_| uniform()).collect();
+        assert_eq!(first, second);
+        assert!(first.iter().all(|x| (0.0..1.0).contains(x)));
+    }
+}
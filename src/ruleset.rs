@@ -0,0 +1,306 @@
+use serde::{Serialize, Deserialize};
+use crate::stat::Stat;
+use crate::hit::{Chance, HitType};
+
+// what the hit chance of an attack depends on, read from the stats of the attacker and the target
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct AttackRoll {
+    pub accuracy: f64,
+    pub fumble: f64,
+    pub crit_chance: f64,
+    pub dodge: f64,
+    pub block: f64,
+}
+
+// a damage type of the weapon, with the resistance of the body part hit to it
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct DamagePart {
+    pub amount: u64,
+    pub magic: bool,
+    pub res: f64,
+}
+
+// what the damage of a hit depends on, read from the stats of the attacker and the target
+#[derive(PartialEq, Debug, Clone)]
+pub struct DamageRoll {
+    pub hit_type: HitType,
+    // weapon damage and main hand efficiency
+    pub mult: f64,
+    pub crit_eff: f64,
+    pub armor_pen: f64,
+    // protection of the body part hit
+    pub protection: u64,
+    pub damage_taken: f64,
+    // block power left to the target
+    pub block: u64,
+    pub damage: Vec<DamagePart>,
+}
+
+// the combat formulas, each one defaulting to the one of the game
+pub trait Rules {
+    // accuracy of the attacker and dodge of the target once they affect each other
+    fn accuracy_and_dodge(&self, self_accuracy: f64, other_dodge: f64) -> (f64, f64) {
+        // below 0 dodge increases other accuracy
+        let accuracy = if other_dodge < 0.0 { 
+            // accuracy cant go above 1
+            if self_accuracy - other_dodge > 1.0 {
+                1.0
+            } else {
+                self_accuracy - other_dodge
+            }
+        } 
+        else {
+            // accuracy cant go above 1
+            if self_accuracy > 1.0 {
+                1.0
+            } else {
+                self_accuracy
+            } 
+        };
+
+        // above 1 accuracy decreases other dodge
+        // dodge cant go below 0
+        let dodge = if other_dodge <= 0.0 { 
+            0.0 
+        } else {
+            if self_accuracy > 1.0 {
+                // dodge cant go below 0 nor above 1
+                (other_dodge - (1.0 - self_accuracy)).clamp(0.0, 1.0)
+            } else {
+                // dodge cant go above 1
+                if other_dodge > 1.0 {
+                    1.0
+                } else {
+                    other_dodge
+                }
+            }
+        };
+        (accuracy, dodge)
+    }
+
+    fn hit_chance(&self, roll: &AttackRoll) -> Chance {
+        let (accuracy, dodge) = self.accuracy_and_dodge(roll.accuracy, roll.dodge);
+        let (fumble, crit_chance, block) = (roll.fumble, roll.crit_chance, roll.block);
+        let half_hit = accuracy * (1.0 - fumble) * dodge + accuracy * fumble * (1.0 - dodge);
+        let normal_hit = accuracy * (1.0 - fumble) * (1.0 - dodge) * (1.0 - crit_chance);
+        let crit_hit = accuracy * (1.0 - fumble) * (1.0 - dodge) * crit_chance;
+
+        Chance::new(
+            crit_hit * (1.0 - block),
+            normal_hit * (1.0 - block),
+            half_hit * (1.0 - block),
+            crit_hit * block,
+            normal_hit * block,
+            half_hit * block
+        )
+    }
+
+    // flat damage reduction of the protection of the body part hit
+    fn armor(&self, protection: u64, armor_pen: f64) -> u64 {
+        (protection as f64 * (1.0 - armor_pen)) as u64
+    }
+
+    // damage dealt to the target and block power it loses
+    fn damage(&self, roll: &DamageRoll) -> (f64, f64) {
+        let hit_type = roll.hit_type;
+        let mut damage = 0;
+
+        let mult_damage = match hit_type {
+            HitType::CritHit => roll.mult * roll.crit_eff,
+            HitType::BlockCritHit => roll.mult * roll.crit_eff,
+            HitType::NormalHit => roll.mult,
+            HitType::BlockNormalHit => roll.mult,
+            HitType::HalfHit => roll.mult / 2.0,
+            HitType::BlockHalfHit => roll.mult / 2.0,
+            _ => 0.0,
+        };
+
+        let mut flat_dmg_red = self.armor(roll.protection, roll.armor_pen);
+        let mut block = match hit_type {
+            HitType::BlockCritHit => roll.block,
+            HitType::BlockNormalHit => roll.block,
+            HitType::BlockHalfHit => roll.block,
+            _ => 0,
+        };
+
+        for part in roll.damage.iter() {
+            let current_dmg = 
+            match part.magic {
+                false =>(part.amount as f64 * mult_damage) as u64,
+                _ => part.amount,
+            };
+
+            let apply_flat_dmg_red = 
+            match part.magic {
+                false => flat_dmg_red,
+                _ => flat_dmg_red / 2,
+            };
+
+            let apply_block = 
+            match part.magic {
+                false => block,
+                _ => block / 2,
+            };
+
+            let percent_damage_reduction = (1.0 - part.res) * roll.damage_taken;
+
+            damage += if apply_block + apply_flat_dmg_red > current_dmg { 0 } else {
+                ((current_dmg - apply_block - apply_flat_dmg_red) as f64 * percent_damage_reduction) as u64
+            };
+
+            if current_dmg > block { 
+                flat_dmg_red = if current_dmg - block > flat_dmg_red { 0 } else { flat_dmg_red + block - current_dmg };
+                block = 0;
+            } else { 
+                block -= current_dmg;
+            }
+        };
+
+        let dmg_block = match hit_type {
+            HitType::BlockCritHit => (roll.block - block) as f64,
+            HitType::BlockNormalHit => (roll.block - block) as f64,
+            HitType::BlockHalfHit => (roll.block - block) as f64,
+            _ => 0.0,
+        };
+        (damage as f64, dmg_block)
+    }
+}
+
+// the formulas of the game
+pub struct Classic;
+
+impl Rules for Classic {}
+
+// accuracy above 1 is lost instead of lowering the dodge of the target,
+// and dodge below 0 no longer raises the accuracy of the attacker
+pub struct BoundedAccuracy;
+
+impl Rules for BoundedAccuracy {
+    fn accuracy_and_dodge(&self, self_accuracy: f64, other_dodge: f64) -> (f64, f64) {
+        (self_accuracy.clamp(0.0, 1.0), other_dodge.clamp(0.0, 1.0))
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Default)]
+pub enum Formulas {
+    #[default]
+    Classic,
+    BoundedAccuracy,
+}
+
+impl Formulas {
+    fn rules(self) -> &'static dyn Rules {
+        match self {
+            Formulas::Classic => &Classic,
+            Formulas::BoundedAccuracy => &BoundedAccuracy,
+        }
+    }
+}
+
+// floors and caps of the values of a stat, the fields left unset being unbounded
+#[derive(Serialize, Deserialize, PartialEq, Debug, Default)]
+pub struct Caps {
+    #[serde(default)]
+    min: Stat,
+    #[serde(default)]
+    max: Stat,
+}
+
+// the rules of the game the combat is simulated with: its formulas and the caps of the stats
+#[derive(Serialize, Deserialize, PartialEq, Debug, Default)]
+pub struct Ruleset {
+    #[serde(default)]
+    formulas: Formulas,
+    #[serde(default)]
+    caps: Caps,
+}
+
+impl Ruleset {
+    // the stat a character fights with, once every effect and bonus is summed up: the caps of the ruleset
+    // can only narrow the bounds of the chances, which always stay probabilities
+    pub fn resolve(&self, stat: &Stat) -> Stat {
+        let mut resolved = stat.clamp(&self.caps.min, &self.caps.max);
+        resolved.bound_probabilities();
+        resolved
+    }
+
+    pub fn rules(&self) -> &'static dyn Rules {
+        self.formulas.rules()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stat(yaml: &str) -> Stat {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_resolve() {
+        let ruleset: Ruleset = serde_yaml::from_str(
+            "caps:\n  min:\n    counter: 0\n    block: 0\n    torso:\n      phy_res: -1\n  max:\n    counter: 1\n    crit_chance: 1\n"
+        ).unwrap();
+        let stunned = stat(
+            "counter: -9.5\nblock: -9.75\ncrit_chance: 1.5\ndodge: -10\naccuracy: 1.25\ndamage:\n  - [Slash, 21]\n\
+            torso:\n  phy_res: -1.5\n  slash_res: 0.5\n"
+        );
+        let expected = stat(
+            "counter: 0\nblock: 0\ncrit_chance: 1\ndodge: -1\naccuracy: 1.25\ndamage:\n  - [Slash, 21]\n\
+            torso:\n  phy_res: -1\n  slash_res: 0.5\n"
+        );
+        assert_eq!(ruleset.resolve(&stunned), expected);
+    }
+
+    #[test]
+    fn test_unset_fields() {
+        // a bound doesn't set a field the stat leaves unset
+        let ruleset: Ruleset = serde_yaml::from_str("caps:\n  min:\n    fumble: 0\n  max:\n    fumble: 1\n").unwrap();
+        assert_eq!(ruleset.resolve(&stat("hp: 100\n")), stat("hp: 100\n"));
+        assert_eq!(Ruleset::default().resolve(&stat("crit_eff: 0.5\n")), stat("crit_eff: 0.5\n"));
+    }
+
+    #[test]
+    fn test_mandatory_bounds() {
+        // without any cap a stun still can't push block below 0, nor a chance above 1
+        let stunned = stat("block: -9.5\ncrit_chance: 1.5\nfumble: 2\nstun_chance: -0.5\ndodge: -10\n");
+        let expected = stat("block: 0\ncrit_chance: 1\nfumble: 1\nstun_chance: 0\ndodge: -1\n");
+        assert_eq!(Ruleset::default().resolve(&stunned), expected);
+        // nor can caps loosen them
+        let ruleset: Ruleset = serde_yaml::from_str("caps:\n  max:\n    crit_chance: 2\n").unwrap();
+        assert_eq!(ruleset.resolve(&stat("crit_chance: 1.5\n")), stat("crit_chance: 1\n"));
+    }
+
+    #[test]
+    fn test_formulas() {
+        // dodge below 0 raises the accuracy of the attacker under the classic formulas only
+        let roll = AttackRoll { accuracy: 0.75, fumble: 0.0, crit_chance: 0.0, dodge: -0.25, block: 0.0 };
+        let bounded: Ruleset = serde_yaml::from_str("formulas: BoundedAccuracy\n").unwrap();
+        assert_eq!(Ruleset::default().rules().hit_chance(&roll), Chance::new(0.0, 1.0, 0.0, 0.0, 0.0, 0.0));
+        assert_eq!(bounded.rules().hit_chance(&roll), Chance::new(0.0, 0.75, 0.0, 0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        // a ruleset only replaces the formulas it changes
+        struct NoArmor;
+        impl Rules for NoArmor {
+            fn armor(&self, _: u64, _: f64) -> u64 {
+                0
+            }
+        }
+        let roll = DamageRoll {
+            hit_type: HitType::NormalHit,
+            mult: 1.0,
+            crit_eff: 1.0,
+            armor_pen: 0.0,
+            protection: 5,
+            damage_taken: 1.0,
+            block: 0,
+            damage: vec![DamagePart { amount: 20, magic: false, res: 0.0 }],
+        };
+        assert_eq!(Classic.damage(&roll), (15.0, 0.0));
+        assert_eq!(NoArmor.damage(&roll), (20.0, 0.0));
+    }
+}
@@ -1,28 +1,39 @@
 use std::ops::{Add, AddAssign};
 use serde::{Serialize, Deserialize};
-use crate::hit::{Chance, HitType, BodyPart, Hit};
-use rand::{self, Rng};
-use std::collections::HashMap;
-use std::iter::zip;
-
-#[derive(Serialize, Deserialize, PartialEq, Debug, Eq, Hash, Clone, Copy)]
-pub enum IdSkills {
-    WarcryOther,
-    Confusion,
-    WarcrySelf,
-    FencerStance,
-    SeizedInitiative,
-    LossInitiative,
-    DisengageSelf,
-    DisengageOther,
+use crate::hit::{HitType, BodyPart, Hit, HitLocation, Size};
+use crate::random;
+use crate::ruleset::{Rules, AttackRoll, DamageRoll, DamagePart};
+
+// effects the engine applies by itself, found in effects.yaml by their id like any other effect
+#[derive(PartialEq, Debug, Eq, Hash, Clone, Copy)]
+pub enum BuiltinEffect {
     Bleeding,
     Daze,
     Stun,
     Knockback,
     Immobilization,
     Stagger,
-    Poisoning,
-    AcidBath,
+    Burning,
+    Chill,
+}
+
+impl BuiltinEffect {
+    pub fn id(self) -> &'static str {
+        match self {
+            BuiltinEffect::Bleeding => "Bleeding",
+            BuiltinEffect::Daze => "Daze",
+            BuiltinEffect::Stun => "Stun",
+            BuiltinEffect::Knockback => "Knockback",
+            BuiltinEffect::Immobilization => "Immobilization",
+            BuiltinEffect::Stagger => "Stagger",
+            BuiltinEffect::Burning => "Burning",
+            BuiltinEffect::Chill => "Chill",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        SECONDARY_EFFECTS.into_iter().find(|e| e.id() == id)
+    }
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
@@ -33,6 +44,13 @@ enum DamageType {
     Rend,
     Poison,
     Caustic,
+    Fire,
+    Frost,
+    Shock,
+    Arcane,
+    Sacred,
+    Unholy,
+    Psionic,
 }
 
 impl DamageType {
@@ -44,10 +62,76 @@ impl DamageType {
             DamageType::Rend => false,
             DamageType::Poison => true,
             DamageType::Caustic => true,
+            DamageType::Fire => true,
+            DamageType::Frost => true,
+            DamageType::Shock => true,
+            DamageType::Arcane => true,
+            DamageType::Sacred => true,
+            DamageType::Unholy => true,
+            DamageType::Psionic => true,
+        }
+    }
+
+    // resistance group the damage type belongs to
+    fn group(self) -> ResGroup {
+        match self {
+            DamageType::Slash => ResGroup::Physical,
+            DamageType::Pierc => ResGroup::Physical,
+            DamageType::Crush => ResGroup::Physical,
+            DamageType::Rend => ResGroup::Physical,
+            DamageType::Poison => ResGroup::Nature,
+            DamageType::Caustic => ResGroup::Nature,
+            DamageType::Fire => ResGroup::Nature,
+            DamageType::Frost => ResGroup::Nature,
+            DamageType::Shock => ResGroup::Nature,
+            DamageType::Arcane => ResGroup::Magic,
+            DamageType::Sacred => ResGroup::Magic,
+            DamageType::Unholy => ResGroup::Magic,
+            DamageType::Psionic => ResGroup::Magic,
+        }
+    }
+
+    // additional effect chance granted by a hit dealing this type of damage
+    fn hit_effect(self) -> Option<(BuiltinEffect, f64)> {
+        match self {
+            DamageType::Fire => Some((BuiltinEffect::Burning, 0.25)),
+            DamageType::Frost => Some((BuiltinEffect::Chill, 0.25)),
+            _ => None,
         }
     }
 }
 
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub enum DotType {
+    Bleed,
+    Poison,
+    Caustic,
+    Fire,
+}
+
+// damage dealt each turn by an effect, resisted by the matching resistance of the target torso
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub struct Dot {
+    damage_type: DotType,
+    flat: Option<u64>,
+    // ratio of the current hp of the target
+    percent: Option<f64>,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum ResGroup {
+    Physical,
+    Nature,
+    Magic,
+}
+
+// ratio of the other body parts chance moved onto the aimed one
+const DEFAULT_AIM_SHIFT: f64 = 0.5;
+
+// bounds of a damage resistance once the group and type resistances are summed up
+const MIN_RES: f64 = -1.0;
+const MAX_RES: f64 = 1.0;
+
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
 enum WeaponType {
     Sword,
@@ -65,68 +149,189 @@ enum WeaponType {
 }
 
 impl WeaponType {
-    fn additional_effect(&self) -> HashMap<IdSkills, f64> {
-        let mut hm = HashMap::new();
+    // additional effect chance granted by a crit, depending on the weapon
+    fn crit_effect(&self) -> Option<(BuiltinEffect, f64)> {
         match self {
-            WeaponType::Sword => hm.insert(IdSkills::Bleeding, 0.5),
-            WeaponType::Mace => hm.insert(IdSkills::Daze, 0.5),
-            WeaponType::TwohSword => hm.insert(IdSkills::Bleeding, 0.75),
-            WeaponType::TwohMace => hm.insert(IdSkills::Daze, 0.75),
-            WeaponType::Spear => hm.insert(IdSkills::Immobilization, 0.75),
-            WeaponType::Bow => hm.insert(IdSkills::Immobilization, 0.5),
-            WeaponType::Crossbow => hm.insert(IdSkills::Knockback, 1.25),
-            WeaponType::Rend => hm.insert(IdSkills::Bleeding, 0.5),
+            WeaponType::Sword => Some((BuiltinEffect::Bleeding, 0.5)),
+            WeaponType::Mace => Some((BuiltinEffect::Daze, 0.5)),
+            WeaponType::TwohSword => Some((BuiltinEffect::Bleeding, 0.75)),
+            WeaponType::TwohMace => Some((BuiltinEffect::Daze, 0.75)),
+            WeaponType::Spear => Some((BuiltinEffect::Immobilization, 0.75)),
+            WeaponType::Bow => Some((BuiltinEffect::Immobilization, 0.5)),
+            WeaponType::Crossbow => Some((BuiltinEffect::Knockback, 1.25)),
+            WeaponType::Rend => Some((BuiltinEffect::Bleeding, 0.5)),
             _ => None,
-        };
-        hm
+        }
     }
 }
 
-trait CustomAdd<T = Self> {
-    type Output;
-    fn add(self, rhs: T) -> Self::Output;
+// secondary effects a hit can apply, in the order they are resolved
+pub const SECONDARY_EFFECTS: [BuiltinEffect; 8] = [
+    BuiltinEffect::Bleeding,
+    BuiltinEffect::Daze,
+    BuiltinEffect::Stun,
+    BuiltinEffect::Knockback,
+    BuiltinEffect::Immobilization,
+    BuiltinEffect::Stagger,
+    BuiltinEffect::Burning,
+    BuiltinEffect::Chill,
+];
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct EffectRoll {
+    id: BuiltinEffect,
+    chance: f64,
+    crit_bonus: f64,
+    res: f64,
+    fortitude: f64,
 }
 
-impl<T: Add<Output = T>> CustomAdd for Option<T> {
-    type Output = Self;
-    fn add(self, other: Option<T>) -> Option<T> {
-        match (self, other) {
-            (Some(i), Some(j)) => Some(i + j),
+impl EffectRoll {
+    pub fn get_id(&self) -> BuiltinEffect {
+        self.id
+    }
+
+    // the effect lands if the attacker chance succeeds and the target fails both its fortitude and resistance
+    pub fn probability(&self) -> f64 {
+        let chance = (self.chance + self.crit_bonus).clamp(0.0, 1.0);
+        let not_resisted = ((1.0 - self.fortitude) * (1.0 - self.res)).clamp(0.0, 1.0);
+        chance * not_resisted
+    }
+}
+
+// how a value combines with the one it's applied on
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Default)]
+pub enum Merge {
+    #[default]
+    Add,
+    Multiply,
+    Override,
+    Min,
+    Max,
+}
+
+impl Merge {
+    fn combine(self, value: f64, other: f64) -> f64 {
+        match self {
+            Merge::Add => value + other,
+            Merge::Multiply => value * other,
+            Merge::Override => other,
+            Merge::Min => value.min(other),
+            Merge::Max => value.max(other),
+        }
+    }
+
+    // a missing value counts as the neutral one of the field
+    fn apply(self, value: Option<f64>, other: Option<f64>, neutral: f64) -> Option<f64> {
+        match (value, other) {
+            (Some(i), Some(j)) => Some(self.combine(i, j)),
             (Some(i), None) => Some(i),
-            (None, Some(j)) => Some(j),
+            (None, Some(j)) => Some(self.combine(neutral, j)),
             (None, None) => None,
         }
     }
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+// combines a numeric field of two stats, knowing the neutral value of the field
+type Zip<'a> = dyn FnMut(Option<f64>, Option<f64>, f64) -> Option<f64> + 'a;
+
+fn zip_u64(value: Option<u64>, other: Option<u64>, neutral: u64, f: &mut Zip) -> Option<u64> {
+    f(value.map(|v| v as f64), other.map(|v| v as f64), neutral as f64).map(|v| v as u64)
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Default)]
 pub struct BobyPart {
     protection: Option<u64>,
     phy_res: Option<f64>,
+    nature_res: Option<f64>,
+    magic_res: Option<f64>,
     slash_res: Option<f64>,
     pierc_res: Option<f64>,
     crush_res: Option<f64>,
     rend_res: Option<f64>,
     poison_res: Option<f64>,
     caustic_res: Option<f64>,
+    fire_res: Option<f64>,
+    frost_res: Option<f64>,
+    shock_res: Option<f64>,
+    arcane_res: Option<f64>,
+    sacred_res: Option<f64>,
+    unholy_res: Option<f64>,
+    psionic_res: Option<f64>,
     bleed_res: Option<f64>,
 }
 
+impl BobyPart {
+    fn get_res(&self, damage_type: DamageType) -> Option<f64> {
+        match damage_type {
+            DamageType::Slash => self.slash_res,
+            DamageType::Pierc => self.pierc_res,
+            DamageType::Crush => self.crush_res,
+            DamageType::Rend => self.rend_res,
+            DamageType::Poison => self.poison_res,
+            DamageType::Caustic => self.caustic_res,
+            DamageType::Fire => self.fire_res,
+            DamageType::Frost => self.frost_res,
+            DamageType::Shock => self.shock_res,
+            DamageType::Arcane => self.arcane_res,
+            DamageType::Sacred => self.sacred_res,
+            DamageType::Unholy => self.unholy_res,
+            DamageType::Psionic => self.psionic_res,
+        }
+    }
+
+    fn get_group_res(&self, group: ResGroup) -> Option<f64> {
+        match group {
+            ResGroup::Physical => self.phy_res,
+            ResGroup::Nature => self.nature_res,
+            ResGroup::Magic => self.magic_res,
+        }
+    }
+
+    // the group resistance adds up with the type one, as shown in the ingame tooltip
+    fn get_resistance(&self, damage_type: DamageType) -> f64 {
+        let res = self.get_group_res(damage_type.group()).unwrap_or(0.0) + self.get_res(damage_type).unwrap_or(0.0);
+        res.clamp(MIN_RES, MAX_RES)
+    }
+}
+
+impl BobyPart {
+    fn zip(&self, other: &BobyPart, f: &mut Zip) -> BobyPart {
+        BobyPart {
+            protection: zip_u64(self.protection, other.protection, 0, f),
+            phy_res: f(self.phy_res, other.phy_res, 0.0),
+            nature_res: f(self.nature_res, other.nature_res, 0.0),
+            magic_res: f(self.magic_res, other.magic_res, 0.0),
+            slash_res: f(self.slash_res, other.slash_res, 0.0),
+            pierc_res: f(self.pierc_res, other.pierc_res, 0.0),
+            crush_res: f(self.crush_res, other.crush_res, 0.0),
+            rend_res: f(self.rend_res, other.rend_res, 0.0),
+            poison_res: f(self.poison_res, other.poison_res, 0.0),
+            caustic_res: f(self.caustic_res, other.caustic_res, 0.0),
+            fire_res: f(self.fire_res, other.fire_res, 0.0),
+            frost_res: f(self.frost_res, other.frost_res, 0.0),
+            shock_res: f(self.shock_res, other.shock_res, 0.0),
+            arcane_res: f(self.arcane_res, other.arcane_res, 0.0),
+            sacred_res: f(self.sacred_res, other.sacred_res, 0.0),
+            unholy_res: f(self.unholy_res, other.unholy_res, 0.0),
+            psionic_res: f(self.psionic_res, other.psionic_res, 0.0),
+            bleed_res: f(self.bleed_res, other.bleed_res, 0.0),
+        }
+    }
+}
+
+fn zip_part(part: Option<BobyPart>, other: Option<BobyPart>, f: &mut Zip) -> Option<BobyPart> {
+    match (part, other) {
+        (None, None) => None,
+        _ => Some(part.unwrap_or_default().zip(&other.unwrap_or_default(), f)),
+    }
+}
+
 impl Add for BobyPart {
     type Output = Self;
 
     fn add(self, other: BobyPart) -> BobyPart {
-        BobyPart {
-            protection: self.protection.add(other.protection),
-            phy_res: self.phy_res.add(other.phy_res),
-            slash_res: self.slash_res.add(other.slash_res),
-            pierc_res: self.pierc_res.add(other.pierc_res),
-            crush_res: self.crush_res.add(other.crush_res),
-            rend_res: self.rend_res.add(other.rend_res),
-            poison_res: self.poison_res.add(other.poison_res),
-            caustic_res: self.caustic_res.add(other.caustic_res),
-            bleed_res: self.bleed_res.add(other.bleed_res),
-        }
+        self.zip(&other, &mut |v, o, n| Merge::Add.apply(v, o, n))
     }
 }
 
@@ -137,7 +342,7 @@ impl AddAssign for BobyPart {
     }
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
 pub struct Stat {
     hp: Option<u64>,
     damage: Option<Vec<(DamageType, u64)>>,
@@ -159,6 +364,13 @@ pub struct Stat {
     block: Option<f64>,
     block_power: Option<u64>,
     block_recovery: Option<f64>,
+    energy: Option<u64>,
+    energy_regen: Option<f64>,
+    // ratio of the max hp restored each turn
+    hp_regen: Option<f64>,
+    // ratio of the damage dealt restored as hp
+    lifesteal: Option<f64>,
+    healing_received: Option<f64>,
     dodge: Option<f64>,
     fortitude: Option<f64>,
     control_res: Option<f64>,
@@ -168,9 +380,11 @@ pub struct Stat {
     legs: Option<BobyPart>,
     torso: Option<BobyPart>,
     head: Option<BobyPart>,
-    flat_damage_receive: Option<u64>,
-    percent_damage_receive: Option<f64>,
-    can_perform_action: Option<bool>,
+    size: Option<Size>,
+    // hit location weights used when attacking, the target size table otherwise
+    hit_location: Option<HitLocation>,
+    aim: Option<BodyPart>,
+    aim_shift: Option<f64>,
 }
 
 impl Stat {
@@ -186,291 +400,320 @@ impl Stat {
         self.block_recovery
     }
 
+    // block chance drops along with the missing block power, nothing is blocked once it's depleted;
+    // the linear scaling is an approximation, the curve of the game isn't known
+    pub fn deplete_block(&mut self, block_power: u64) {
+        let max_block = self.block_power.unwrap_or(0);
+        let ratio = if max_block == 0 { 0.0 } else { (block_power as f64 / max_block as f64).min(1.0) };
+        self.block = self.block.map(|b| b * ratio);
+    }
+
+    pub fn get_energy(&self) -> Option<u64> {
+        self.energy
+    }
+
+    pub fn get_energy_regen(&self) -> Option<f64> {
+        self.energy_regen
+    }
+
+    pub fn get_hp_regen(&self) -> Option<f64> {
+        self.hp_regen
+    }
+
+    // hp drained by a hit, only the hp it actually removed from the target counting
+    pub fn drain(&self, damage: f64, removed: f64) -> f64 {
+        damage.min(removed) * self.lifesteal.unwrap_or(0.0)
+    }
+
+    // healing actually received, once modified by the healing received of the target
+    pub fn healing(&self, amount: f64) -> f64 {
+        (amount * self.healing_received.unwrap_or(1.0)).max(0.0)
+    }
+
+    // adds bonuses onto a base stat: a value the base leaves unset counts from its neutral one
+    pub fn with_bonus(self, bonus: Stat) -> Stat {
+        self.merge(bonus, Merge::Add)
+    }
+
     pub fn get_counter(&self) -> Option<f64> {
         self.counter
     }
 
-    pub fn get_additional_chance(&self) -> HashMap<IdSkills, f64> {
-        let mut additional_chance = HashMap::new();
-        additional_chance.insert(IdSkills::Bleeding, self.bleed_chance.unwrap_or(0.0));
-        additional_chance.insert(IdSkills::Daze, self.daze_chance.unwrap_or(0.0));
-        additional_chance.insert(IdSkills::Stun, self.stun_chance.unwrap_or(0.0));
-        additional_chance.insert(IdSkills::Knockback, self.knockback_chance.unwrap_or(0.0));
-        additional_chance.insert(IdSkills::Immobilization, self.immobilization_chance.unwrap_or(0.0));
-        additional_chance.insert(IdSkills::Stagger, self.stagger_chance.unwrap_or(0.0));
-        additional_chance
-    }
-
-    pub fn get_additional_res(&self, bodypart: BodyPart) -> HashMap<IdSkills, f64> {
-        let fortitude = 1.0 - self.fortitude.unwrap_or(0.0);
-        let mut additional_res = HashMap::new();
-        additional_res.insert(IdSkills::Bleeding, match bodypart {
-            BodyPart::LeftHand => fortitude * (1.0 - self.hands.unwrap().bleed_res.unwrap_or(0.0)),
-            BodyPart::RightHand => fortitude * (1.0 - self.hands.unwrap().bleed_res.unwrap_or(0.0)),
-            BodyPart::LeftLeg => fortitude * (1.0 - self.legs.unwrap().bleed_res.unwrap_or(0.0)),
-            BodyPart::RightLeg => fortitude * (1.0 - self.legs.unwrap().bleed_res.unwrap_or(0.0)),
-            BodyPart::Torso => fortitude * (1.0 - self.torso.unwrap().bleed_res.unwrap_or(0.0)),
-            BodyPart::Head => fortitude * (1.0 - self.head.unwrap().bleed_res.unwrap_or(0.0)),
-            _ => fortitude,
-        });
-        additional_res.insert(IdSkills::Daze, fortitude * (1.0 - self.control_res.unwrap_or(0.0)));
-        additional_res.insert(IdSkills::Stun, fortitude * (1.0 - self.control_res.unwrap_or(0.0)));
-        additional_res.insert(IdSkills::Knockback, fortitude * (1.0 - self.move_res.unwrap_or(0.0)));
-        additional_res.insert(IdSkills::Immobilization, fortitude * (1.0 - self.move_res.unwrap_or(0.0)));
-        additional_res.insert(IdSkills::Stagger, fortitude * (1.0 - self.move_res.unwrap_or(0.0)));
-        additional_res
+    // physical damage is scaled through the weapon damage, magic damage ignoring it has its amount scaled instead
+    pub fn scale_damage(&mut self, mult: f64) {
+        self.weapon_dmg = Some(self.weapon_dmg.unwrap_or(1.0) * mult);
+        if let Some(damage) = self.damage.as_mut() {
+            for (_, d) in damage.iter_mut().filter(|(t, _)| t.is_magic()) {
+                *d = (*d as f64 * mult) as u64;
+            }
+        }
     }
 
-    pub fn attack(&self, other: &Stat) -> Hit{
-        let self_can_perform_action = self.can_perform_action.unwrap_or(true);
-        if !self_can_perform_action {
+    pub fn force_crit(&mut self) {
+        self.crit_chance = Some(1.0);
+    }
 
-            let chance = Chance::new(0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
-            Hit::new(chance, BodyPart::None)
+    fn get_body_part(&self, bodypart: BodyPart) -> Option<BobyPart> {
+        match bodypart {
+            BodyPart::RightLeg => self.legs,
+            BodyPart::LeftLeg => self.legs,
+            BodyPart::RightHand => self.hands,
+            BodyPart::LeftHand => self.hands,
+            BodyPart::Torso => self.torso,
+            BodyPart::Head => self.head,
+            BodyPart::None => self.torso,
+        }
+    }
 
-        } else {
-            let self_accuracy = self.accuracy.unwrap_or(1.0);
-            let self_fumble = self.fumble.unwrap_or(0.0);
-            let self_crit_chance = self.crit_chance.unwrap_or(0.0);
-
-            let other_dodge = other.dodge.unwrap_or(0.0);
-            let other_block = other.block.unwrap_or(0.0);
-
-            // below 0 dodge increases other accuracy
-            let accuracy = if other_dodge < 0.0 { 
-                // accuracy cant go above 1
-                if self_accuracy - other_dodge > 1.0 {
-                    1.0
-                } else {
-                    self_accuracy - other_dodge
-                }
-            } 
-            else {
-                // accuracy cant go above 1
-                if self_accuracy > 1.0 {
-                    1.0
-                } else {
-                    self_accuracy
-                } 
-            };
-
-            // above 1 accuracy decreases other dodge
-            // dodge cant go below 0
-            let dodge = if other_dodge <= 0.0 { 
-                0.0 
-            } else {
-                if self_accuracy > 1.0 {
-                    // dodge cant go below 0
-                    if other_dodge - (1.0 - self_accuracy) < 0.0 {
-                        0.0
-                    // dodge cant go above 1
-                    } else if other_dodge - (1.0 - self_accuracy) > 1.0 {
-                        1.0
-                    } else {
-                        other_dodge - (1.0 - self_accuracy)
-                    }
-                } else {
-                    // dodge cant go above 1
-                    if other_dodge > 1.0 {
-                        1.0
-                    } else {
-                        other_dodge
-                    }
-                }
-            };
-            let fumble = if self_fumble < 0.0 { 0.0 } else { if self_fumble > 1.0 { 1.0 } else { self_fumble } };
-            let half_hit = accuracy * (1.0 - fumble) * dodge + accuracy * fumble * (1.0 - dodge);
-            let normal_hit = accuracy * (1.0 - fumble) * (1.0 - dodge) * (1.0 - self_crit_chance);
-            let crit_hit = accuracy * (1.0 - fumble) * (1.0 - dodge) * self_crit_chance;
-
-            let chance = Chance::new(
-                crit_hit * (1.0 - other_block),
-                normal_hit * (1.0 - other_block),
-                half_hit * (1.0 - other_block),
-                crit_hit * other_block,
-                normal_hit * other_block,
-                half_hit * other_block
-            );
-
-            let mut rng = rand::thread_rng();
-            let body_part = match rng.gen_range(0..6) {
-                0 => BodyPart::RightLeg,
-                1 => BodyPart::LeftLeg,
-                2 => BodyPart::RightHand,
-                3 => BodyPart::LeftHand,
-                4 => BodyPart::Torso,
-                5 => BodyPart::Head,
-                _ => BodyPart::None,
-            };
-            
-            Hit::new(chance, body_part)
-        }  
+    fn get_additional_chance(&self, id: BuiltinEffect) -> f64 {
+        match id {
+            BuiltinEffect::Bleeding => self.bleed_chance,
+            BuiltinEffect::Daze => self.daze_chance,
+            BuiltinEffect::Stun => self.stun_chance,
+            BuiltinEffect::Knockback => self.knockback_chance,
+            BuiltinEffect::Immobilization => self.immobilization_chance,
+            BuiltinEffect::Stagger => self.stagger_chance,
+            _ => None,
+        }.unwrap_or(0.0) + self.get_damage_type_chance(id)
     }
 
-    pub fn get_damage(
-        &self, other: &Stat, body_part: BodyPart, hit_type: HitType, other_block_value: u64
-    ) -> (f64, f64) {
+    fn get_damage_type_chance(&self, id: BuiltinEffect) -> f64 {
+        self.damage.iter().flatten()
+            .filter_map(|(t, _)| t.hit_effect())
+            .filter(|(e, _)| *e == id)
+            .map(|(_, c)| c)
+            .sum()
+    }
 
-        match hit_type {
-            HitType::NoHit => return (0.0, 0.0),
-            _ => (),
-        };
+    fn get_additional_res(&self, id: BuiltinEffect, bodypart: BodyPart) -> f64 {
+        match id {
+            BuiltinEffect::Bleeding => match bodypart {
+                BodyPart::None => None,
+                _ => self.get_body_part(bodypart).and_then(|b| b.bleed_res),
+            },
+            BuiltinEffect::Daze => self.control_res,
+            BuiltinEffect::Stun => self.control_res,
+            BuiltinEffect::Knockback => self.move_res,
+            BuiltinEffect::Immobilization => self.move_res,
+            BuiltinEffect::Stagger => self.move_res,
+            BuiltinEffect::Burning => self.get_body_part(bodypart).map(|b| b.get_resistance(DamageType::Fire)),
+            BuiltinEffect::Chill => self.get_body_part(bodypart).map(|b| b.get_resistance(DamageType::Frost)),
+        }.unwrap_or(0.0)
+    }
 
-        let self_crit_eff = self.crit_eff.unwrap_or(1.0);
-        let self_armor_pen = self.armor_pen.unwrap_or(0.0);
-        let self_weapon_dmg = self.weapon_dmg.unwrap_or(1.0);
-        let self_main_hand_eff = self.main_hand_eff.unwrap_or(1.0);
-
-        let self_damage = self.damage.clone().unwrap_or(vec![(DamageType::Rend, 0)]);
-
-        let other_body_part = match body_part {
-            BodyPart::RightLeg => other.legs,
-            BodyPart::LeftLeg => other.legs,
-            BodyPart::RightHand => other.hands,
-            BodyPart::LeftHand => other.hands,
-            BodyPart::Torso => other.torso,
-            BodyPart::Head => other.head,
-            BodyPart::None => other.torso,
-        }.unwrap();
-
-        let mut damage = 0;
-        let crit_eff = if self_crit_eff < 1.0 {1.0} else {self_crit_eff};
-        let normal_mult_damage = self_weapon_dmg * self_main_hand_eff;
-
-        let mult_damage = match hit_type {
-            HitType::CritHit => normal_mult_damage * crit_eff,
-            HitType::BlockCritHit => normal_mult_damage * crit_eff,
-            HitType::NormalHit => normal_mult_damage,
-            HitType::BlockNormalHit => normal_mult_damage,
-            HitType::HalfHit => normal_mult_damage / 2.0,
-            HitType::BlockHalfHit => normal_mult_damage / 2.0,
-            _ => 0.0,
+    // chance, crit bonus, resistance and fortitude of each secondary effect the hit can apply
+    pub fn effect_table(&self, other: &Stat, bodypart_hit: BodyPart, is_crit: bool) -> Vec<EffectRoll> {
+        let crit_effect = if is_crit {
+            self.weapon_type.unwrap_or(WeaponType::Rend).crit_effect()
+        } else {
+            None
         };
+        SECONDARY_EFFECTS.iter().map(|id| EffectRoll {
+            crit_bonus: match crit_effect {
+                Some((crit_id, bonus)) if crit_id == *id => bonus,
+                _ => 0.0,
+            },
+            ..other.resist_roll(*id, self.get_additional_chance(*id), bodypart_hit)
+        }).collect()
+    }
 
-        let mut flat_dmg_red = (other_body_part.protection.unwrap_or(0) as f64 * (1.0 - self_armor_pen)) as u64;
-        let mut block = match hit_type {
-            HitType::BlockCritHit => other_block_value,
-            HitType::BlockNormalHit => other_block_value,
-            HitType::BlockHalfHit => other_block_value,
-            _ => 0,
+    // roll of an effect with the given chance against the resistance and fortitude of this stat
+    pub fn resist_roll(&self, id: BuiltinEffect, chance: f64, bodypart_hit: BodyPart) -> EffectRoll {
+        EffectRoll {
+            id,
+            chance,
+            crit_bonus: 0.0,
+            res: self.get_additional_res(id, bodypart_hit),
+            fortitude: self.fortitude.unwrap_or(0.0),
+        }
+    }
+
+    // fumble, crit chance and block are kept between 0 and 1 when the ruleset resolves the stat
+    pub fn attack(&self, other: &Stat, rules: &dyn Rules) -> Hit {
+        let roll = AttackRoll {
+            accuracy: self.accuracy.unwrap_or(1.0),
+            fumble: self.fumble.unwrap_or(0.0),
+            crit_chance: self.crit_chance.unwrap_or(0.0),
+            dodge: other.dodge.unwrap_or(0.0),
+            block: other.block.unwrap_or(0.0),
         };
+        Hit::new(rules.hit_chance(&roll), self.get_hit_location(other).draw())
+    }
 
-        for (t, d) in self_damage {
-            let t_is_magic = t.is_magic();
-    
-            let current_dmg = 
-            match t_is_magic {
-                false =>(d as f64 * mult_damage) as u64,
-                _ => d,
-            };
-
-            let apply_flat_dmg_red = 
-            match t_is_magic {
-                false => flat_dmg_red,
-                _ => flat_dmg_red / 2,
-            };
-
-            let apply_block = 
-            match t_is_magic {
-                false => block,
-                _ => block / 2,
-            };
-
-            let percent_damage_reduction = 
-            match t {
-                DamageType::Slash => (1.0 - other_body_part.slash_res.unwrap_or(0.0)) * other.damage_taken.unwrap_or(1.0),
-                DamageType::Pierc => (1.0 - other_body_part.pierc_res.unwrap_or(0.0)) * other.damage_taken.unwrap_or(1.0),
-                DamageType::Crush => (1.0 - other_body_part.crush_res.unwrap_or(0.0)) * other.damage_taken.unwrap_or(1.0),
-                DamageType::Rend => (1.0 - other_body_part.rend_res.unwrap_or(0.0)) * other.damage_taken.unwrap_or(1.0),
-                DamageType::Poison => (1.0 - other_body_part.poison_res.unwrap_or(0.0)) * other.damage_taken.unwrap_or(1.0),
-                DamageType::Caustic => (1.0 - other_body_part.caustic_res.unwrap_or(0.0)) * other.damage_taken.unwrap_or(1.0),
-            };
-
-            damage += if apply_block + apply_flat_dmg_red > current_dmg { 0 } else {
-                ((current_dmg - apply_block - apply_flat_dmg_red) as f64 * percent_damage_reduction) as u64
-            };
-
-            if current_dmg > block { 
-                flat_dmg_red = if current_dmg - block > flat_dmg_red { 0 } else { flat_dmg_red + block - current_dmg };
-                block = 0;
-            } else { 
-                block = block - current_dmg;
-            }
+    // an aimed attack moves part of the chance to hit other body parts onto the aimed one
+    pub fn get_hit_location(&self, other: &Stat) -> HitLocation {
+        let location = self.hit_location.unwrap_or_else(|| other.size.unwrap_or_default().hit_location());
+        match self.aim {
+            Some(part) => location.aim(part, self.aim_shift.unwrap_or(DEFAULT_AIM_SHIFT)),
+            None => location,
+        }
+    }
+
+    // damage dealt to the other stat and block power it loses
+    pub fn get_damage(
+        &self, other: &Stat, body_part: BodyPart, hit_type: HitType, other_block_value: u64, rules: &dyn Rules
+    ) -> (f64, f64) {
+        if hit_type == HitType::NoHit {
+            return (0.0, 0.0);
+        }
+        let other_body_part = other.get_body_part(body_part).unwrap();
+        let roll = DamageRoll {
+            hit_type,
+            mult: self.weapon_dmg.unwrap_or(1.0) * self.main_hand_eff.unwrap_or(1.0),
+            crit_eff: self.crit_eff.unwrap_or(1.0),
+            armor_pen: self.armor_pen.unwrap_or(0.0),
+            protection: other_body_part.protection.unwrap_or(0),
+            damage_taken: other.damage_taken.unwrap_or(1.0),
+            block: other_block_value,
+            damage: self.damage.clone().unwrap_or(vec![(DamageType::Rend, 0)]).into_iter()
+                .map(|(t, d)| DamagePart { amount: d, magic: t.is_magic(), res: other_body_part.get_resistance(t) })
+                .collect(),
         };
+        rules.damage(&roll)
+    }
 
-        let dmg_block = match hit_type {
-            HitType::BlockCritHit => (other_block_value - block) as f64,
-            HitType::BlockNormalHit => (other_block_value - block) as f64,
-            HitType::BlockHalfHit => (other_block_value - block) as f64,
-            _ => 0.0,
+    // damage dealt by one tick of a damage over time effect, each stack ticking once
+    pub fn dot_damage(&self, dot: &Dot, stacks: u64, current_hp: u64) -> f64 {
+        let body = self.get_body_part(BodyPart::Torso);
+        let res = match dot.damage_type {
+            DotType::Bleed => body.and_then(|b| b.bleed_res).unwrap_or(0.0).clamp(MIN_RES, MAX_RES),
+            DotType::Poison => body.map(|b| b.get_resistance(DamageType::Poison)).unwrap_or(0.0),
+            DotType::Caustic => body.map(|b| b.get_resistance(DamageType::Caustic)).unwrap_or(0.0),
+            DotType::Fire => body.map(|b| b.get_resistance(DamageType::Fire)).unwrap_or(0.0),
         };
-        (damage as f64, dmg_block)
+        let tick = dot.flat.unwrap_or(0) as f64 + dot.percent.unwrap_or(0.0) * current_hp as f64;
+        (tick * stacks as f64 * (1.0 - res) * self.damage_taken.unwrap_or(1.0)).max(0.0)
     }
 
-    pub fn residual_damage(&self) -> f64 {
-        self.percent_damage_receive.unwrap_or(0.0) * self.hp.unwrap_or(0) as f64 + 
-        self.flat_damage_receive.unwrap_or(0) as f64
+    pub fn additional_effect(&self, other: &Stat, bodypart_hit: BodyPart, is_crit: bool) -> Vec<(EffectRoll, bool)> {
+        self.effect_table(other, bodypart_hit, is_crit).into_iter()
+            .map(|r| (r, random::uniform() < r.probability()))
+            .collect()
     }
+}
 
-    pub fn additional_effect(&self, other: &Stat, bodypart_hit: BodyPart, is_crit: bool) -> HashMap<IdSkills, bool> {
-        let hash_chance = 
-        if is_crit {
-            let weapon_type = self.weapon_type.unwrap_or(WeaponType::Rend);
-            let crit_effect = weapon_type.additional_effect();
-            self.get_additional_chance().iter().map(|(s, x)| (*s, x + crit_effect.get(s).unwrap_or(&0.0))).collect()
-        } else {
-            self.get_additional_chance()
-        };
-        let mut arr_chance = [0.0f64; 6];
-        rand::thread_rng().fill(&mut arr_chance[..]);
-        let mut arr_res = [0.0f64; 6];
-        rand::thread_rng().fill(&mut arr_res[..]);
+impl Stat {
+    // applies f to each numeric field of both stats, the values without arithmetic being left unset
+    fn zip(&self, other: &Stat, f: &mut Zip) -> Stat {
+        Stat{
+            hp: zip_u64(self.hp, other.hp, 0, f),
+            weapon_dmg: f(self.weapon_dmg, other.weapon_dmg, 1.0),
+            main_hand_eff: f(self.main_hand_eff, other.main_hand_eff, 1.0),
+            armor_pen: f(self.armor_pen, other.armor_pen, 0.0),
+            accuracy: f(self.accuracy, other.accuracy, 1.0),
+            crit_chance: f(self.crit_chance, other.crit_chance, 0.0),
+            crit_eff: f(self.crit_eff, other.crit_eff, 1.0),
+            counter: f(self.counter, other.counter, 0.0),
+            fumble: f(self.fumble, other.fumble, 0.0),
+            bleed_chance: f(self.bleed_chance, other.bleed_chance, 0.0),
+            daze_chance: f(self.daze_chance, other.daze_chance, 0.0),
+            stun_chance: f(self.stun_chance, other.stun_chance, 0.0),
+            knockback_chance: f(self.knockback_chance, other.knockback_chance, 0.0),
+            immobilization_chance: f(self.immobilization_chance, other.immobilization_chance, 0.0),
+            stagger_chance: f(self.stagger_chance, other.stagger_chance, 0.0),
+            block: f(self.block, other.block, 0.0),
+            block_power: zip_u64(self.block_power, other.block_power, 0, f),
+            block_recovery: f(self.block_recovery, other.block_recovery, 0.0),
+            energy: zip_u64(self.energy, other.energy, 0, f),
+            energy_regen: f(self.energy_regen, other.energy_regen, 0.0),
+            hp_regen: f(self.hp_regen, other.hp_regen, 0.0),
+            lifesteal: f(self.lifesteal, other.lifesteal, 0.0),
+            healing_received: f(self.healing_received, other.healing_received, 1.0),
+            dodge: f(self.dodge, other.dodge, 0.0),
+            fortitude: f(self.fortitude, other.fortitude, 0.0),
+            control_res: f(self.control_res, other.control_res, 0.0),
+            move_res: f(self.move_res, other.move_res, 0.0),
+            damage_taken: f(self.damage_taken, other.damage_taken, 1.0),
+            hands: zip_part(self.hands, other.hands, f),
+            legs: zip_part(self.legs, other.legs, f),
+            torso: zip_part(self.torso, other.torso, f),
+            head: zip_part(self.head, other.head, f),
+            aim_shift: f(self.aim_shift, other.aim_shift, 0.0),
+            ..Stat::default()
+        }
+    }
+
+    pub fn merge(self, other: Stat, merge: Merge) -> Stat {
+        self.combine(other, &mut |v, o, n| merge.apply(v, o, n))
+    }
+
+    // values without arithmetic (damage, weapon type, size, hit location, aim) are always overridden,
+    // f combining the others
+    fn combine(self, other: Stat, f: &mut Zip) -> Stat {
+        let zipped = self.zip(&other, f);
+        Stat {
+            damage: other.damage.or(self.damage),
+            weapon_type: other.weapon_type.or(self.weapon_type),
+            size: other.size.or(self.size),
+            hit_location: other.hit_location.or(self.hit_location),
+            aim: other.aim.or(self.aim),
+            ..zipped
+        }
+    }
+
+    // bonus worth a percentage of the values of this stat, for the fields given in percents (10 for 10%)
+    pub fn percent_of(&self, percents: &Stat) -> Stat {
+        self.zip(percents, &mut |v, p, n| p.map(|p| v.unwrap_or(n) * p / 100.0))
+    }
+
+    // each value raised to its floor and lowered to its cap, the fields without floor nor cap being kept as is
+    pub fn clamp(&self, min: &Stat, max: &Stat) -> Stat {
+        let floored = self.zip(min, &mut |v, m, _| match (v, m) {
+            (Some(v), Some(m)) => Some(v.max(m)),
+            (v, _) => v,
+        });
+        let capped = floored.zip(max, &mut |v, m, _| match (v, m) {
+            (Some(v), Some(m)) => Some(v.min(m)),
+            (v, _) => v,
+        });
+        Stat {
+            damage: self.damage.clone(),
+            weapon_type: self.weapon_type,
+            size: self.size,
+            hit_location: self.hit_location,
+            aim: self.aim,
+            ..capped
+        }
+    }
+
+    // the chances are probabilities whatever the caps, dodge going down to -1 since below 0 it raises the accuracy of the attacker
+    pub fn bound_probabilities(&mut self) {
+        let chances = [
+            &mut self.crit_chance, &mut self.fumble, &mut self.block, &mut self.counter,
+            &mut self.bleed_chance, &mut self.daze_chance, &mut self.stun_chance,
+            &mut self.knockback_chance, &mut self.immobilization_chance, &mut self.stagger_chance,
+        ];
+        for chance in chances.into_iter().flatten() {
+            *chance = chance.clamp(0.0, 1.0);
+        }
+        if let Some(dodge) = self.dodge.as_mut() {
+            *dodge = dodge.clamp(-1.0, 1.0);
+        }
+    }
 
-        let hash_res = other.get_additional_res(bodypart_hit);
+    pub fn scale(&self, factor: f64) -> Stat {
+        self.zip(&Stat::default(), &mut |v, _, _| v.map(|v| v * factor))
+    }
 
-        zip(zip(hash_chance, arr_chance), zip(hash_res, arr_res))
-        .map(|(((s, x_c), y_c), ((_, x_r), y_r))| (s, y_c < x_c && y_r < x_r)).collect::<HashMap<IdSkills, bool>>()
+    // sum of the values of this stat weighted by the fields given in weights
+    pub fn weigh(&self, weights: &Stat) -> f64 {
+        let mut total = 0.0;
+        self.zip(weights, &mut |v, w, n| {
+            if let Some(w) = w {
+                total += v.unwrap_or(n) * w;
+            }
+            None
+        });
+        total
     }
 }
 
 impl Add for Stat {
     type Output = Self;
 
+    // bonuses are summed from nothing, with_bonus adding the total onto a stat
     fn add(self, other: Stat) -> Stat {
-        Stat{
-            hp: self.hp.add(other.hp),
-            damage: self.damage,
-            weapon_type: self.weapon_type,
-            weapon_dmg: self.weapon_dmg.add(other.weapon_dmg),
-            main_hand_eff: self.main_hand_eff.add(other.main_hand_eff),
-            armor_pen: self.armor_pen.add(other.armor_pen),
-            accuracy: self.accuracy.add(other.accuracy),
-            crit_chance: self.crit_chance.add(other.crit_chance),
-            crit_eff: self.crit_eff.add(other.crit_eff),
-            counter: self.counter.add(other.counter),
-            fumble: self.fumble.add(other.fumble),
-            bleed_chance: self.bleed_chance.add(other.bleed_chance),
-            daze_chance: self.daze_chance.add(other.daze_chance),
-            stun_chance: self.stun_chance.add(other.stun_chance),
-            knockback_chance: self.knockback_chance.add(other.knockback_chance),
-            immobilization_chance: self.immobilization_chance.add(other.immobilization_chance),
-            stagger_chance: self.stagger_chance.add(other.stagger_chance),
-            block: self.block.add(other.block),
-            block_power: self.block_power.add(other.block_power),
-            block_recovery: self.block_recovery.add(other.block_recovery),
-            dodge: self.dodge.add(other.dodge),
-            fortitude: self.fortitude.add(other.fortitude),
-            control_res: self.control_res.add(other.control_res),
-            move_res: self.move_res.add(other.move_res),
-            damage_taken: self.damage_taken.add(other.damage_taken),
-            hands: self.hands.add(other.hands),
-            legs: self.legs.add(other.legs),
-            torso: self.torso.add(other.torso),
-            head: self.head.add(other.head),
-            flat_damage_receive: self.flat_damage_receive.add(other.flat_damage_receive),
-            percent_damage_receive: self.percent_damage_receive.add(other.percent_damage_receive),
-            can_perform_action: other.can_perform_action,
-        }
+        self.combine(other, &mut |v, o, _| Merge::Add.apply(v, o, 0.0))
     }
 }
 
@@ -484,6 +727,8 @@ impl AddAssign for Stat {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::hit::Chance;
+    use crate::ruleset::Classic;
 
     #[test]
     fn test_basic_attack() {
@@ -491,6 +736,8 @@ mod tests {
         let dummy_body_part = BobyPart {
             protection: Some(0),
             phy_res: Some(0.0),
+            nature_res: Some(0.0),
+            magic_res: Some(0.0),
             slash_res: Some(0.0),
             pierc_res: Some(0.0),
             crush_res: Some(0.0),
@@ -498,6 +745,13 @@ mod tests {
             bleed_res: Some(0.0),
             poison_res: Some(0.0),
             caustic_res: Some(0.0),
+            fire_res: Some(0.0),
+            frost_res: Some(0.0),
+            shock_res: Some(0.0),
+            arcane_res: Some(0.0),
+            sacred_res: Some(0.0),
+            unholy_res: Some(0.0),
+            psionic_res: Some(0.0),
         };
 
         let player_stats = Stat
@@ -522,22 +776,28 @@ mod tests {
             block: Some(0.0),
             block_power: Some(0),
             block_recovery: Some(0.0),
+            energy: Some(0),
+            energy_regen: Some(0.0),
+            hp_regen: Some(0.0),
+            lifesteal: Some(0.0),
+            healing_received: Some(1.0),
             dodge: Some(0.0),
             fortitude: Some(0.0),
             control_res: Some(0.0),
             move_res: Some(0.0),
             damage_taken: Some(1.0),
-            hands: Some(dummy_body_part.clone()),
-            legs: Some(dummy_body_part.clone()),
-            torso: Some(dummy_body_part.clone()),
-            head: Some(dummy_body_part.clone()),
-            flat_damage_receive: Some(0),
-            percent_damage_receive: Some(0.0),
-            can_perform_action: Some(true),
+            hands: Some(dummy_body_part),
+            legs: Some(dummy_body_part),
+            torso: Some(dummy_body_part),
+            head: Some(dummy_body_part),
+            size: None,
+            hit_location: None,
+            aim: None,
+            aim_shift: None,
         };
 
         let dummy_stat = player_stats.clone();
-        let hit_player = player_stats.attack(&dummy_stat);
+        let hit_player = player_stats.attack(&dummy_stat, &Classic);
         
         assert_eq!(hit_player.get_chance(), Chance::new(0.0, 1.0, 0.0, 0.0, 0.0, 0.0));
     }
@@ -549,6 +809,8 @@ mod tests {
         let dummy_body_part = BobyPart {
             protection: Some(0),
             phy_res: Some(0.0),
+            nature_res: Some(0.0),
+            magic_res: Some(0.0),
             slash_res: Some(0.0),
             pierc_res: Some(0.0),
             crush_res: Some(0.0),
@@ -556,6 +818,13 @@ mod tests {
             bleed_res: Some(0.0),
             poison_res: Some(0.0),
             caustic_res: Some(0.0),
+            fire_res: Some(0.0),
+            frost_res: Some(0.0),
+            shock_res: Some(0.0),
+            arcane_res: Some(0.0),
+            sacred_res: Some(0.0),
+            unholy_res: Some(0.0),
+            psionic_res: Some(0.0),
         };
 
         let player_stats = Stat
@@ -580,22 +849,28 @@ mod tests {
             block: Some(0.0),
             block_power: Some(0),
             block_recovery: Some(0.0),
+            energy: Some(0),
+            energy_regen: Some(0.0),
+            hp_regen: Some(0.0),
+            lifesteal: Some(0.0),
+            healing_received: Some(1.0),
             dodge: Some(0.0),
             fortitude: Some(0.0),
             control_res: Some(0.0),
             move_res: Some(0.0),
             damage_taken: Some(1.0),
-            hands: Some(dummy_body_part.clone()),
-            legs: Some(dummy_body_part.clone()),
-            torso: Some(dummy_body_part.clone()),
-            head: Some(dummy_body_part.clone()),
-            flat_damage_receive: Some(0),
-            percent_damage_receive: Some(0.0),
-            can_perform_action: Some(true),
+            hands: Some(dummy_body_part),
+            legs: Some(dummy_body_part),
+            torso: Some(dummy_body_part),
+            head: Some(dummy_body_part),
+            size: None,
+            hit_location: None,
+            aim: None,
+            aim_shift: None,
         };
 
         let dummy_stat = player_stats.clone();
-        let hit_player = player_stats.attack(&dummy_stat);
+        let hit_player = player_stats.attack(&dummy_stat, &Classic);
         
         assert_eq!(hit_player.get_chance(), Chance::new(1.0, 0.0, 0.0, 0.0, 0.0, 0.0));
     }
@@ -607,6 +882,8 @@ mod tests {
         let dummy_body_part = BobyPart {
             protection: Some(0),
             phy_res: Some(0.0),
+            nature_res: Some(0.0),
+            magic_res: Some(0.0),
             slash_res: Some(0.0),
             pierc_res: Some(0.0),
             crush_res: Some(0.0),
@@ -614,6 +891,13 @@ mod tests {
             bleed_res: Some(0.0),
             poison_res: Some(0.0),
             caustic_res: Some(0.0),
+            fire_res: Some(0.0),
+            frost_res: Some(0.0),
+            shock_res: Some(0.0),
+            arcane_res: Some(0.0),
+            sacred_res: Some(0.0),
+            unholy_res: Some(0.0),
+            psionic_res: Some(0.0),
         };
 
         let player_stats = Stat
@@ -638,22 +922,28 @@ mod tests {
             block: Some(0.0),
             block_power: Some(0),
             block_recovery: Some(0.0),
+            energy: Some(0),
+            energy_regen: Some(0.0),
+            hp_regen: Some(0.0),
+            lifesteal: Some(0.0),
+            healing_received: Some(1.0),
             dodge: Some(0.0),
             fortitude: Some(0.0),
             control_res: Some(0.0),
             move_res: Some(0.0),
             damage_taken: Some(1.0),
-            hands: Some(dummy_body_part.clone()),
-            legs: Some(dummy_body_part.clone()),
-            torso: Some(dummy_body_part.clone()),
-            head: Some(dummy_body_part.clone()),
-            flat_damage_receive: Some(0),
-            percent_damage_receive: Some(0.0),
-            can_perform_action: Some(true),
+            hands: Some(dummy_body_part),
+            legs: Some(dummy_body_part),
+            torso: Some(dummy_body_part),
+            head: Some(dummy_body_part),
+            size: None,
+            hit_location: None,
+            aim: None,
+            aim_shift: None,
         };
 
         let dummy_stat = player_stats.clone();
-        let hit_player = player_stats.attack(&dummy_stat);
+        let hit_player = player_stats.attack(&dummy_stat, &Classic);
         
         assert_eq!(hit_player.get_chance(), Chance::new(0.0, 0.0, 1.0, 0.0, 0.0, 0.0));
     }
@@ -665,6 +955,8 @@ mod tests {
         let dummy_body_part = BobyPart {
             protection: Some(0),
             phy_res: Some(0.0),
+            nature_res: Some(0.0),
+            magic_res: Some(0.0),
             slash_res: Some(0.0),
             pierc_res: Some(0.0),
             crush_res: Some(0.0),
@@ -672,6 +964,13 @@ mod tests {
             bleed_res: Some(0.0),
             poison_res: Some(0.0),
             caustic_res: Some(0.0),
+            fire_res: Some(0.0),
+            frost_res: Some(0.0),
+            shock_res: Some(0.0),
+            arcane_res: Some(0.0),
+            sacred_res: Some(0.0),
+            unholy_res: Some(0.0),
+            psionic_res: Some(0.0),
         };
 
         let player_stats = Stat
@@ -696,22 +995,28 @@ mod tests {
             block: Some(0.0),
             block_power: Some(0),
             block_recovery: Some(0.0),
+            energy: Some(0),
+            energy_regen: Some(0.0),
+            hp_regen: Some(0.0),
+            lifesteal: Some(0.0),
+            healing_received: Some(1.0),
             dodge: Some(1.0),
             fortitude: Some(0.0),
             control_res: Some(0.0),
             move_res: Some(0.0),
             damage_taken: Some(1.0),
-            hands: Some(dummy_body_part.clone()),
-            legs: Some(dummy_body_part.clone()),
-            torso: Some(dummy_body_part.clone()),
-            head: Some(dummy_body_part.clone()),
-            flat_damage_receive: Some(0),
-            percent_damage_receive: Some(0.0),
-            can_perform_action: Some(true),
+            hands: Some(dummy_body_part),
+            legs: Some(dummy_body_part),
+            torso: Some(dummy_body_part),
+            head: Some(dummy_body_part),
+            size: None,
+            hit_location: None,
+            aim: None,
+            aim_shift: None,
         };
 
         let dummy_stat = player_stats.clone();
-        let hit_player = player_stats.attack(&dummy_stat);
+        let hit_player = player_stats.attack(&dummy_stat, &Classic);
         
         assert_eq!(hit_player.get_chance(), Chance::new(0.0, 0.0, 1.0, 0.0, 0.0, 0.0));
     }
@@ -723,6 +1028,8 @@ mod tests {
         let dummy_body_part = BobyPart {
             protection: Some(0),
             phy_res: Some(0.0),
+            nature_res: Some(0.0),
+            magic_res: Some(0.0),
             slash_res: Some(0.0),
             pierc_res: Some(0.0),
             crush_res: Some(0.0),
@@ -730,6 +1037,13 @@ mod tests {
             bleed_res: Some(0.0),
             poison_res: Some(0.0),
             caustic_res: Some(0.0),
+            fire_res: Some(0.0),
+            frost_res: Some(0.0),
+            shock_res: Some(0.0),
+            arcane_res: Some(0.0),
+            sacred_res: Some(0.0),
+            unholy_res: Some(0.0),
+            psionic_res: Some(0.0),
         };
 
         let player_stats = Stat
@@ -754,22 +1068,28 @@ mod tests {
             block: Some(0.0),
             block_power: Some(0),
             block_recovery: Some(0.0),
+            energy: Some(0),
+            energy_regen: Some(0.0),
+            hp_regen: Some(0.0),
+            lifesteal: Some(0.0),
+            healing_received: Some(1.0),
             dodge: Some(1.0),
             fortitude: Some(0.0),
             control_res: Some(0.0),
             move_res: Some(0.0),
             damage_taken: Some(1.0),
-            hands: Some(dummy_body_part.clone()),
-            legs: Some(dummy_body_part.clone()),
-            torso: Some(dummy_body_part.clone()),
-            head: Some(dummy_body_part.clone()),
-            flat_damage_receive: Some(0),
-            percent_damage_receive: Some(0.0),
-            can_perform_action: Some(true),
+            hands: Some(dummy_body_part),
+            legs: Some(dummy_body_part),
+            torso: Some(dummy_body_part),
+            head: Some(dummy_body_part),
+            size: None,
+            hit_location: None,
+            aim: None,
+            aim_shift: None,
         };
 
         let dummy_stat = player_stats.clone();
-        let hit_player = player_stats.attack(&dummy_stat);
+        let hit_player = player_stats.attack(&dummy_stat, &Classic);
         
         assert_eq!(hit_player.get_chance(), Chance::new(0.0, 0.0, 0.0, 0.0, 0.0, 0.0));
     }
@@ -783,6 +1103,8 @@ mod tests {
         let dummy_body_part = BobyPart {
             protection: Some(0),
             phy_res: Some(0.0),
+            nature_res: Some(0.0),
+            magic_res: Some(0.0),
             slash_res: Some(0.0),
             pierc_res: Some(0.0),
             crush_res: Some(0.0),
@@ -790,6 +1112,13 @@ mod tests {
             bleed_res: Some(0.0),
             poison_res: Some(0.0),
             caustic_res: Some(0.0),
+            fire_res: Some(0.0),
+            frost_res: Some(0.0),
+            shock_res: Some(0.0),
+            arcane_res: Some(0.0),
+            sacred_res: Some(0.0),
+            unholy_res: Some(0.0),
+            psionic_res: Some(0.0),
         };
 
         let player_stats = Stat
@@ -814,22 +1143,28 @@ mod tests {
             block: Some(1.0),
             block_power: Some(block_power),
             block_recovery: Some(0.0),
+            energy: Some(0),
+            energy_regen: Some(0.0),
+            hp_regen: Some(0.0),
+            lifesteal: Some(0.0),
+            healing_received: Some(1.0),
             dodge: Some(0.0),
             fortitude: Some(0.0),
             control_res: Some(0.0),
             move_res: Some(0.0),
             damage_taken: Some(1.0),
-            hands: Some(dummy_body_part.clone()),
-            legs: Some(dummy_body_part.clone()),
-            torso: Some(dummy_body_part.clone()),
-            head: Some(dummy_body_part.clone()),
-            flat_damage_receive: Some(0),
-            percent_damage_receive: Some(0.0),
-            can_perform_action: Some(true),
+            hands: Some(dummy_body_part),
+            legs: Some(dummy_body_part),
+            torso: Some(dummy_body_part),
+            head: Some(dummy_body_part),
+            size: None,
+            hit_location: None,
+            aim: None,
+            aim_shift: None,
         };
 
         let dummy_stat = player_stats.clone();
-        let hit_player = player_stats.attack(&dummy_stat);
+        let hit_player = player_stats.attack(&dummy_stat, &Classic);
         
         assert_eq!(hit_player.get_chance(), Chance::new(0.0, 0.0, 0.0, 0.0, 1.0, 0.0));
     }
@@ -846,6 +1181,8 @@ mod tests {
         let dummy_body_part = BobyPart {
             protection: Some(0),
             phy_res: Some(0.0),
+            nature_res: Some(0.0),
+            magic_res: Some(0.0),
             slash_res: Some(0.0),
             pierc_res: Some(0.0),
             crush_res: Some(0.0),
@@ -853,6 +1190,13 @@ mod tests {
             bleed_res: Some(0.0),
             poison_res: Some(0.0),
             caustic_res: Some(0.0),
+            fire_res: Some(0.0),
+            frost_res: Some(0.0),
+            shock_res: Some(0.0),
+            arcane_res: Some(0.0),
+            sacred_res: Some(0.0),
+            unholy_res: Some(0.0),
+            psionic_res: Some(0.0),
         };
 
         let player_stats = Stat
@@ -877,22 +1221,28 @@ mod tests {
             block: Some(1.0),
             block_power: Some(block_power),
             block_recovery: Some(0.0),
+            energy: Some(0),
+            energy_regen: Some(0.0),
+            hp_regen: Some(0.0),
+            lifesteal: Some(0.0),
+            healing_received: Some(1.0),
             dodge: Some(0.0),
             fortitude: Some(0.0),
             control_res: Some(0.0),
             move_res: Some(0.0),
             damage_taken: Some(1.0),
-            hands: Some(dummy_body_part.clone()),
-            legs: Some(dummy_body_part.clone()),
-            torso: Some(dummy_body_part.clone()),
-            head: Some(dummy_body_part.clone()),
-            flat_damage_receive: Some(0),
-            percent_damage_receive: Some(0.0),
-            can_perform_action: Some(true),
+            hands: Some(dummy_body_part),
+            legs: Some(dummy_body_part),
+            torso: Some(dummy_body_part),
+            head: Some(dummy_body_part),
+            size: None,
+            hit_location: None,
+            aim: None,
+            aim_shift: None,
         };
 
         let dummy_stat = player_stats.clone();
-        let hit_player = player_stats.attack(&dummy_stat);
+        let hit_player = player_stats.attack(&dummy_stat, &Classic);
         
         assert_eq!(hit_player.get_chance(), Chance::new(0.0, 0.0, 0.0, 0.0, 1.0, 0.0));
     }
@@ -910,6 +1260,8 @@ mod tests {
         let dummy_body_part = BobyPart {
             protection: Some(protection),
             phy_res: Some(0.0),
+            nature_res: Some(0.0),
+            magic_res: Some(0.0),
             slash_res: Some(0.0),
             pierc_res: Some(0.0),
             crush_res: Some(0.0),
@@ -917,6 +1269,13 @@ mod tests {
             bleed_res: Some(0.0),
             poison_res: Some(0.0),
             caustic_res: Some(0.0),
+            fire_res: Some(0.0),
+            frost_res: Some(0.0),
+            shock_res: Some(0.0),
+            arcane_res: Some(0.0),
+            sacred_res: Some(0.0),
+            unholy_res: Some(0.0),
+            psionic_res: Some(0.0),
         };
 
         let player_stats = Stat
@@ -941,22 +1300,28 @@ mod tests {
             block: Some(1.0),
             block_power: Some(block_power),
             block_recovery: Some(0.0),
+            energy: Some(0),
+            energy_regen: Some(0.0),
+            hp_regen: Some(0.0),
+            lifesteal: Some(0.0),
+            healing_received: Some(1.0),
             dodge: Some(0.0),
             fortitude: Some(0.0),
             control_res: Some(0.0),
             move_res: Some(0.0),
             damage_taken: Some(1.0),
-            hands: Some(dummy_body_part.clone()),
-            legs: Some(dummy_body_part.clone()),
-            torso: Some(dummy_body_part.clone()),
-            head: Some(dummy_body_part.clone()),
-            flat_damage_receive: Some(0),
-            percent_damage_receive: Some(0.0),
-            can_perform_action: Some(true),
+            hands: Some(dummy_body_part),
+            legs: Some(dummy_body_part),
+            torso: Some(dummy_body_part),
+            head: Some(dummy_body_part),
+            size: None,
+            hit_location: None,
+            aim: None,
+            aim_shift: None,
         };
 
         let dummy_stat = player_stats.clone();
-        let hit_player = player_stats.attack(&dummy_stat);
+        let hit_player = player_stats.attack(&dummy_stat, &Classic);
         
         assert_eq!(hit_player.get_chance(), Chance::new(0.0, 0.0, 0.0, 0.0, 1.0, 0.0));
     }
@@ -975,6 +1340,8 @@ mod tests {
         let dummy_body_part = BobyPart {
             protection: Some(protection),
             phy_res: Some(0.0),
+            nature_res: Some(0.0),
+            magic_res: Some(0.0),
             slash_res: Some(1.0 - slash_res),
             pierc_res: Some(0.0),
             crush_res: Some(0.0),
@@ -982,6 +1349,13 @@ mod tests {
             bleed_res: Some(0.0),
             poison_res: Some(0.0),
             caustic_res: Some(0.0),
+            fire_res: Some(0.0),
+            frost_res: Some(0.0),
+            shock_res: Some(0.0),
+            arcane_res: Some(0.0),
+            sacred_res: Some(0.0),
+            unholy_res: Some(0.0),
+            psionic_res: Some(0.0),
         };
 
         let player_stats = Stat
@@ -1006,22 +1380,28 @@ mod tests {
             block: Some(1.0),
             block_power: Some(block_power),
             block_recovery: Some(0.0),
+            energy: Some(0),
+            energy_regen: Some(0.0),
+            hp_regen: Some(0.0),
+            lifesteal: Some(0.0),
+            healing_received: Some(1.0),
             dodge: Some(0.0),
             fortitude: Some(0.0),
             control_res: Some(0.0),
             move_res: Some(0.0),
             damage_taken: Some(1.0),
-            hands: Some(dummy_body_part.clone()),
-            legs: Some(dummy_body_part.clone()),
-            torso: Some(dummy_body_part.clone()),
-            head: Some(dummy_body_part.clone()),
-            flat_damage_receive: Some(0),
-            percent_damage_receive: Some(0.0),
-            can_perform_action: Some(true),
+            hands: Some(dummy_body_part),
+            legs: Some(dummy_body_part),
+            torso: Some(dummy_body_part),
+            head: Some(dummy_body_part),
+            size: None,
+            hit_location: None,
+            aim: None,
+            aim_shift: None,
         };
 
         let dummy_stat = player_stats.clone();
-        let hit_player = player_stats.attack(&dummy_stat);
+        let hit_player = player_stats.attack(&dummy_stat, &Classic);
         
         assert_eq!(hit_player.get_chance(), Chance::new(0.0, 0.0, 0.0, 0.0, 1.0, 0.0));
     }
@@ -1037,11 +1417,12 @@ mod tests {
         let base_protection = 3;
         let slash_res = 0.5;
         let armor_pen = 0.2;
-        let protection = (base_protection as f64 * (1.0 - armor_pen)) as u64;
         
         let dummy_body_part = BobyPart {
             protection: Some(base_protection),
             phy_res: Some(0.0),
+            nature_res: Some(0.0),
+            magic_res: Some(0.0),
             slash_res: Some(1.0 - slash_res),
             pierc_res: Some(0.0),
             crush_res: Some(0.0),
@@ -1049,6 +1430,13 @@ mod tests {
             bleed_res: Some(0.0),
             poison_res: Some(0.0),
             caustic_res: Some(0.0),
+            fire_res: Some(0.0),
+            frost_res: Some(0.0),
+            shock_res: Some(0.0),
+            arcane_res: Some(0.0),
+            sacred_res: Some(0.0),
+            unholy_res: Some(0.0),
+            psionic_res: Some(0.0),
         };
  
         let player_stats = Stat
@@ -1073,23 +1461,335 @@ mod tests {
             block: Some(1.0),
             block_power: Some(block_power),
             block_recovery: Some(0.0),
+            energy: Some(0),
+            energy_regen: Some(0.0),
+            hp_regen: Some(0.0),
+            lifesteal: Some(0.0),
+            healing_received: Some(1.0),
             dodge: Some(0.0),
             fortitude: Some(0.0),
             control_res: Some(0.0),
             move_res: Some(0.0),
             damage_taken: Some(1.0),
-            hands: Some(dummy_body_part.clone()),
-            legs: Some(dummy_body_part.clone()),
-            torso: Some(dummy_body_part.clone()),
-            head: Some(dummy_body_part.clone()),
-            flat_damage_receive: Some(0),
-            percent_damage_receive: Some(0.0),
-            can_perform_action: Some(true),
+            hands: Some(dummy_body_part),
+            legs: Some(dummy_body_part),
+            torso: Some(dummy_body_part),
+            head: Some(dummy_body_part),
+            size: None,
+            hit_location: None,
+            aim: None,
+            aim_shift: None,
         };
 
         let dummy_stat = player_stats.clone();
-        let hit_player = player_stats.attack(&dummy_stat);
+        let hit_player = player_stats.attack(&dummy_stat, &Classic);
         
         assert_eq!(hit_player.get_chance(), Chance::new(0.0, 0.0, 0.0, 0.0, 1.0, 0.0));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_scale_magic_damage() {
+        // an ability multiplier scales the magic damage as much as the physical one
+        let mut attacker: Stat = serde_yaml::from_str("damage:\n  - [Slash, 10]\n  - [Poison, 10]\n").unwrap();
+        attacker.scale_damage(1.5);
+        let target: Stat = serde_yaml::from_str("torso:\n  protection: 0\n").unwrap();
+        let (damage, _) = attacker.get_damage(&target, BodyPart::Torso, HitType::NormalHit, 0, &Classic);
+        assert_eq!(damage, (15 + 15) as f64);
+    }
+
+    fn effect_table_fixture() -> (Stat, Stat) {
+        let attacker: Stat = serde_yaml::from_str(
+            "weapon_type: Sword\nbleed_chance: 0.1\ndaze_chance: 0.2\nstun_chance: 1.0\n"
+        ).unwrap();
+        let target: Stat = serde_yaml::from_str(
+            "fortitude: 0.2\ncontrol_res: 1.0\nmove_res: 0.25\ntorso:\n  bleed_res: 0.25\nhead:\n  bleed_res: 0.5\n"
+        ).unwrap();
+        (attacker, target)
+    }
+
+    fn probabilities(table: &[EffectRoll]) -> Vec<(BuiltinEffect, f64)> {
+        table.iter().map(|r| (r.get_id(), (r.probability() * 1e6).round() / 1e6)).collect()
+    }
+
+    #[test]
+    fn test_effect_table_order() {
+        let (attacker, target) = effect_table_fixture();
+        let table = attacker.effect_table(&target, BodyPart::Torso, false);
+        assert_eq!(table.iter().map(|r| r.get_id()).collect::<Vec<BuiltinEffect>>(), SECONDARY_EFFECTS.to_vec());
+    }
+
+    #[test]
+    fn test_effect_table_normal_hit() {
+        let (attacker, target) = effect_table_fixture();
+        let table = attacker.effect_table(&target, BodyPart::Torso, false);
+        assert_eq!(probabilities(&table), vec![
+            (BuiltinEffect::Bleeding, 0.06),
+            (BuiltinEffect::Daze, 0.0),
+            (BuiltinEffect::Stun, 0.0),
+            (BuiltinEffect::Knockback, 0.0),
+            (BuiltinEffect::Immobilization, 0.0),
+            (BuiltinEffect::Stagger, 0.0),
+            (BuiltinEffect::Burning, 0.0),
+            (BuiltinEffect::Chill, 0.0),
+        ]);
+    }
+
+    #[test]
+    fn test_effect_table_crit_hit() {
+        let (attacker, target) = effect_table_fixture();
+        let table = attacker.effect_table(&target, BodyPart::Head, true);
+        assert_eq!(probabilities(&table), vec![
+            (BuiltinEffect::Bleeding, 0.24),
+            (BuiltinEffect::Daze, 0.0),
+            (BuiltinEffect::Stun, 0.0),
+            (BuiltinEffect::Knockback, 0.0),
+            (BuiltinEffect::Immobilization, 0.0),
+            (BuiltinEffect::Stagger, 0.0),
+            (BuiltinEffect::Burning, 0.0),
+            (BuiltinEffect::Chill, 0.0),
+        ]);
+    }
+
+    #[test]
+    fn test_effect_table_res_pairing() {
+        let (attacker, mut target) = effect_table_fixture();
+        target.control_res = Some(0.5);
+        target.stagger_chance = Some(1.0);
+        let table = attacker.effect_table(&target, BodyPart::Torso, false);
+        assert_eq!(probabilities(&table), vec![
+            (BuiltinEffect::Bleeding, 0.06),
+            (BuiltinEffect::Daze, 0.08),
+            (BuiltinEffect::Stun, 0.4),
+            (BuiltinEffect::Knockback, 0.0),
+            (BuiltinEffect::Immobilization, 0.0),
+            (BuiltinEffect::Stagger, 0.0),
+            (BuiltinEffect::Burning, 0.0),
+            (BuiltinEffect::Chill, 0.0),
+        ]);
+    }
+
+    #[test]
+    fn test_additional_effect_certain() {
+        let attacker: Stat = serde_yaml::from_str("bleed_chance: 1.0\n").unwrap();
+        let target: Stat = serde_yaml::from_str("torso:\n  bleed_res: 0.0\n").unwrap();
+        for _ in 0..100 {
+            let rolls = attacker.additional_effect(&target, BodyPart::Torso, false);
+            assert!(rolls.iter().all(|(r, applied)| *applied == (r.get_id() == BuiltinEffect::Bleeding)));
+        }
+    }
+
+    #[test]
+    fn test_resist_roll() {
+        let target: Stat = serde_yaml::from_str("fortitude: 0.2\ncontrol_res: 0.5\nmove_res: 0.75\n").unwrap();
+        let stun = target.resist_roll(BuiltinEffect::Stun, 0.5, BodyPart::Torso);
+        let knockback = target.resist_roll(BuiltinEffect::Knockback, 1.0, BodyPart::Torso);
+        assert_eq!(probabilities(&[stun, knockback]), vec![(BuiltinEffect::Stun, 0.2), (BuiltinEffect::Knockback, 0.2)]);
+    }
+
+    #[test]
+    fn test_magic_damage() {
+        let attacker: Stat = serde_yaml::from_str(
+            "damage:\n  - [Slash, 10]\n  - [Fire, 10]\n  - [Frost, 10]\n  - [Unholy, 10]\n"
+        ).unwrap();
+        let target: Stat = serde_yaml::from_str(
+            "torso:\n  protection: 4\n  fire_res: 0.5\n  frost_res: 0.2\n  unholy_res: -0.5\n"
+        ).unwrap();
+        // protection is used up by the slash damage, magic damage only goes through its resistance
+        let (damage, _) = attacker.get_damage(&target, BodyPart::Torso, HitType::NormalHit, 0, &Classic);
+        assert_eq!(damage, (6 + 5 + 8 + 15) as f64);
+    }
+
+    #[test]
+    fn test_magic_block() {
+        let attacker: Stat = serde_yaml::from_str("damage:\n  - [Shock, 10]\n").unwrap();
+        let target: Stat = serde_yaml::from_str("torso:\n  protection: 0\n").unwrap();
+        // block is halved on magic damage
+        let (damage, damage_block) = attacker.get_damage(&target, BodyPart::Torso, HitType::BlockNormalHit, 8, &Classic);
+        assert_eq!((damage, damage_block), (6.0, 8.0));
+    }
+
+    #[test]
+    fn test_magic_hit_effect() {
+        let attacker: Stat = serde_yaml::from_str("damage:\n  - [Fire, 10]\n  - [Frost, 10]\n").unwrap();
+        let target: Stat = serde_yaml::from_str("torso:\n  fire_res: 0.2\n  frost_res: 1.0\n").unwrap();
+        let table = attacker.effect_table(&target, BodyPart::Torso, false);
+        let probas = probabilities(&table);
+        assert_eq!(probas[6], (BuiltinEffect::Burning, 0.2));
+        assert_eq!(probas[7], (BuiltinEffect::Chill, 0.0));
+    }
+
+    #[test]
+    fn test_group_res() {
+        let part: BobyPart = serde_yaml::from_str(
+            "phy_res: 0.1\nnature_res: 0.2\nmagic_res: 0.3\nslash_res: 0.05\nfire_res: -0.1\npsionic_res: 0.1\n"
+        ).unwrap();
+        assert_eq!(part.get_resistance(DamageType::Slash), 0.1 + 0.05);
+        assert_eq!(part.get_resistance(DamageType::Crush), 0.1);
+        assert_eq!(part.get_resistance(DamageType::Fire), 0.2 - 0.1);
+        assert_eq!(part.get_resistance(DamageType::Psionic), 0.3 + 0.1);
+    }
+
+    #[test]
+    fn test_field_merge() {
+        let base: Stat = serde_yaml::from_str("dodge: 0.25\nweapon_type: Sword\ndamage:\n  - [Slash, 20]\n").unwrap();
+        let other: Stat = serde_yaml::from_str("dodge: 0.25\nweapon_type: Mace\naim: Head\n").unwrap();
+        let expected: Stat = serde_yaml::from_str(
+            "dodge: 0.5\nweapon_type: Mace\ndamage:\n  - [Slash, 20]\naim: Head\n"
+        ).unwrap();
+        assert_eq!(base + other, expected);
+    }
+
+    #[test]
+    fn test_forced_merge() {
+        let base: Stat = serde_yaml::from_str("dodge: 0.5\ncounter: 0.25\nhead:\n  protection: 4\n").unwrap();
+        let effect: Stat = serde_yaml::from_str("dodge: 0.25\nfumble: 0.5\nhead:\n  protection: 2\n").unwrap();
+        let multiplied: Stat = serde_yaml::from_str("dodge: 0.125\ncounter: 0.25\nfumble: 0.0\nhead:\n  protection: 8\n").unwrap();
+        let min: Stat = serde_yaml::from_str("dodge: 0.25\ncounter: 0.25\nfumble: 0.0\nhead:\n  protection: 2\n").unwrap();
+        let max: Stat = serde_yaml::from_str("dodge: 0.5\ncounter: 0.25\nfumble: 0.5\nhead:\n  protection: 4\n").unwrap();
+        let overridden: Stat = serde_yaml::from_str("dodge: 0.25\ncounter: 0.25\nfumble: 0.5\nhead:\n  protection: 2\n").unwrap();
+        assert_eq!(base.clone().merge(effect.clone(), Merge::Multiply), multiplied);
+        assert_eq!(base.clone().merge(effect.clone(), Merge::Min), min);
+        assert_eq!(base.clone().merge(effect.clone(), Merge::Max), max);
+        assert_eq!(base.merge(effect, Merge::Override), overridden);
+    }
+
+    #[test]
+    fn test_bonus_on_unset_field() {
+        // a profile without damage_taken takes the full damage, a -25% bonus leaving 75% of it
+        let base: Stat = serde_yaml::from_str("dodge: 0.5\n").unwrap();
+        let bonus: Stat = serde_yaml::from_str("damage_taken: -0.25\naccuracy: -0.05\ndodge: 0.1\n").unwrap();
+        let expected: Stat = serde_yaml::from_str("damage_taken: 0.75\naccuracy: 0.95\ndodge: 0.6\n").unwrap();
+        assert_eq!(base.with_bonus(bonus.clone()), expected);
+        // bonuses summed among themselves start from nothing
+        let summed: Stat = serde_yaml::from_str("damage_taken: -0.5\naccuracy: -0.1\ndodge: 0.2\n").unwrap();
+        assert_eq!(bonus.clone() + bonus, summed);
+    }
+
+    #[test]
+    fn test_percent_of() {
+        let base: Stat = serde_yaml::from_str("hp: 200\nblock: 0.5\ndodge: 0.25\nhead:\n  protection: 4\n").unwrap();
+        let percents: Stat = serde_yaml::from_str("hp: 10\nblock: 50\naccuracy: 25\nhead:\n  protection: 50\n").unwrap();
+        let expected: Stat = serde_yaml::from_str("hp: 20\nblock: 0.25\naccuracy: 0.25\nhead:\n  protection: 2\n").unwrap();
+        assert_eq!(base.percent_of(&percents), expected);
+    }
+
+    #[test]
+    fn test_scale_and_weigh() {
+        let stat: Stat = serde_yaml::from_str("hp: 10\ndodge: 0.5\ncounter: 0.25\n").unwrap();
+        let scaled: Stat = serde_yaml::from_str("hp: 25\ndodge: 1.25\ncounter: 0.625\n").unwrap();
+        assert_eq!(stat.scale(2.5), scaled);
+        let weights: Stat = serde_yaml::from_str("dodge: 0.5\ncounter: 1\naccuracy: 0.5\n").unwrap();
+        assert_eq!(stat.weigh(&weights), 0.25 + 0.25 + 0.5);
+    }
+
+    #[test]
+    fn test_res_cap() {
+        let part: BobyPart = serde_yaml::from_str("phy_res: 0.8\nslash_res: 0.5\nnature_res: -0.8\nfire_res: -0.5\n").unwrap();
+        assert_eq!(part.get_resistance(DamageType::Slash), MAX_RES);
+        assert_eq!(part.get_resistance(DamageType::Fire), MIN_RES);
+    }
+
+    #[test]
+    fn test_characters_tooltip() {
+        // resistances displayed ingame by the tooltips of the main character
+        let file = serde_yaml::from_str(include_str!("../data/characters.yaml")).unwrap();
+        let (_, profiles) = crate::version::untag("characters.yaml", file).unwrap();
+        let characters = crate::profile::resolve(&profiles).unwrap();
+        let items = serde_yaml::from_str(include_str!("../data/items.yaml")).unwrap();
+        let attributes = serde_yaml::from_str(include_str!("../data/attributes.yaml")).unwrap();
+        let passives = serde_yaml::from_str(include_str!("../data/passives.yaml")).unwrap();
+        let main = characters["main"].build(&items, &attributes, &passives).unwrap();
+        // slash, pierc, crush, rend and bleed resistances as read on the ingame tooltip of each body part
+        let tooltip = [
+            (BodyPart::Head, [0.16, 0.16, 0.21, 0.21, 0.46]),
+            (BodyPart::Torso, [0.13, 0.13, 0.16, 0.18, 0.28]),
+            (BodyPart::RightHand, [0.16, 0.16, 0.21, 0.21, 0.18]),
+            (BodyPart::RightLeg, [0.14, 0.14, 0.14, 0.19, 0.48]),
+        ];
+        let types = [DamageType::Slash, DamageType::Pierc, DamageType::Crush, DamageType::Rend];
+        for (part, res) in tooltip {
+            let body = main.get_body_part(part).unwrap();
+            for (t, r) in types.iter().zip(res.iter()) {
+                assert!((body.get_resistance(*t) - r).abs() < 1e-9, "{:?} {:?}", part, t);
+            }
+            assert!((body.bleed_res.unwrap_or(0.0) - res[4]).abs() < 1e-9, "{:?} bleed", part);
+        }
+    }
+
+    #[test]
+    fn test_hit_location() {
+        let attacker: Stat = serde_yaml::from_str("accuracy: 1.0\n").unwrap();
+        let bear: Stat = serde_yaml::from_str("size: Large\n").unwrap();
+        assert_eq!(attacker.get_hit_location(&bear), Size::Large.hit_location());
+
+        let custom: Stat = serde_yaml::from_str(
+            "hit_location:\n  right_leg: 1\n  left_leg: 1\n  right_hand: 1\n  left_hand: 1\n  torso: 1\n  head: 1\n"
+        ).unwrap();
+        assert_eq!(custom.get_hit_location(&bear).get_chance(BodyPart::Head), 1.0 / 6.0);
+    }
+
+    #[test]
+    fn test_aimed_attack() {
+        let attacker: Stat = serde_yaml::from_str("accuracy: 1.0\n").unwrap();
+        let aim: Stat = serde_yaml::from_str("aim: Head\naim_shift: 1.0\n").unwrap();
+        let target: Stat = serde_yaml::from_str("torso:\n  protection: 0\n").unwrap();
+        let aimed = attacker + aim;
+        for _ in 0..100 {
+            assert_eq!(aimed.attack(&target, &Classic).get_bodypart_hit(), BodyPart::Head);
+        }
+        let location = aimed.get_hit_location(&target);
+        assert_eq!(location, Size::Medium.hit_location().aim(BodyPart::Head, 1.0));
+    }
+
+    #[test]
+    fn test_block_depletion() {
+        let shield: Stat = serde_yaml::from_str("block: 0.4\nblock_power: 20\n").unwrap();
+        let attacker: Stat = serde_yaml::from_str("accuracy: 1.0\n").unwrap();
+        let blocked = |block_power| {
+            let mut target = shield.clone();
+            target.deplete_block(block_power);
+            attacker.attack(&target, &Classic).get_chance().into_iter().skip(3).sum::<f64>()
+        };
+        assert_eq!(blocked(20), 0.4);
+        assert_eq!(blocked(10), 0.2);
+        assert_eq!(blocked(0), 0.0);
+
+        let mut no_shield: Stat = serde_yaml::from_str("block: 0.4\n").unwrap();
+        no_shield.deplete_block(0);
+        assert_eq!(attacker.attack(&no_shield, &Classic).get_chance().into_iter().skip(3).sum::<f64>(), 0.0);
+    }
+
+    #[test]
+    fn test_dot_damage() {
+        let target: Stat = serde_yaml::from_str(
+            "damage_taken: 1.0\ntorso:\n  bleed_res: 0.5\n  nature_res: 0.1\n  poison_res: 0.1\n"
+        ).unwrap();
+        let bleed: Dot = serde_yaml::from_str("damage_type: Bleed\nflat: 4\npercent: 0.01\n").unwrap();
+        let poison: Dot = serde_yaml::from_str("damage_type: Poison\nflat: 10\n").unwrap();
+        let acid: Dot = serde_yaml::from_str("damage_type: Caustic\npercent: 0.1\n").unwrap();
+        assert_eq!(target.dot_damage(&bleed, 1, 200), 3.0);
+        assert_eq!(target.dot_damage(&bleed, 3, 200), 9.0);
+        assert_eq!(target.dot_damage(&poison, 2, 200), 16.0);
+        // caustic goes through the nature resistance only
+        assert_eq!(target.dot_damage(&acid, 1, 50), 4.5);
+    }
+
+    #[test]
+    fn test_healing_received() {
+        let stat: Stat = serde_yaml::from_str("healing_received: 1.0\n").unwrap();
+        let cursed: Stat = serde_yaml::from_str("healing_received: -0.5\n").unwrap();
+        assert_eq!(stat.healing(10.0), 10.0);
+        assert_eq!((stat.clone() + cursed.clone()).healing(10.0), 5.0);
+        assert_eq!((cursed.clone() + cursed).healing(10.0), 0.0);
+    }
+
+    #[test]
+    fn test_drain() {
+        let stat: Stat = serde_yaml::from_str("lifesteal: 0.5\n").unwrap();
+        assert_eq!(stat.drain(10.0, 12.0), 5.0);
+        // a killing blow only drains the hp the target had left
+        assert_eq!(stat.drain(30.0, 12.0), 6.0);
+        let stat: Stat = serde_yaml::from_str("hp: 50\n").unwrap();
+        assert_eq!(stat.drain(30.0, 12.0), 0.0);
+    }
+}
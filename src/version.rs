@@ -0,0 +1,101 @@
+use std::fmt;
+use serde::de::DeserializeOwned;
+use serde_yaml::{Mapping, Value};
+
+// a game version such as 0.8.2.10, the last number being the hotfix
+#[derive(PartialEq, Debug, Clone)]
+pub struct GameVersion(Vec<u64>);
+
+impl GameVersion {
+    pub fn parse(version: &str) -> Result<Self, String> {
+        version.split('.')
+            .map(|n| n.parse::<u64>())
+            .collect::<Result<Vec<u64>, _>>()
+            .map(GameVersion)
+            .map_err(|_| format!("Invalid game version: {}", version))
+    }
+
+    // versions of the same release only differ by their hotfix
+    fn release(&self) -> &[u64] {
+        &self.0[..self.0.len().saturating_sub(1)]
+    }
+}
+
+impl fmt::Display for GameVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let numbers: Vec<String> = self.0.iter().map(|n| n.to_string()).collect();
+        write!(f, "{}", numbers.join("."))
+    }
+}
+
+// the data of a file tagged with the game version it comes from under game_version
+pub fn untag<T: DeserializeOwned>(path: &str, mut file: Mapping) -> Result<(GameVersion, T), String> {
+    let version = match file.remove("game_version") {
+        Some(Value::String(v)) => GameVersion::parse(&v).map_err(|e| format!("{}: {}", path, e))?,
+        Some(_) => return Err(format!("{}: game_version expects a version such as 0.8.2.10", path)),
+        None => return Err(format!("{}: missing game_version", path)),
+    };
+    let data = serde_yaml::from_value(Value::Mapping(file)).map_err(|e| format!("{}: {}", path, e))?;
+    Ok((version, data))
+}
+
+// every file must come from the release of the first one, a different hotfix only giving a warning
+pub fn check(files: &[(&str, &GameVersion)]) -> Result<Vec<String>, String> {
+    let mut warnings = Vec::new();
+    if let Some((first_path, first)) = files.first() {
+        for (path, version) in files.iter().skip(1) {
+            if version.release() != first.release() {
+                return Err(format!("{} is tagged {} but {} is tagged {}", path, version, first_path, first));
+            }
+            if version != first {
+                warnings.push(format!("{} is tagged {} but {} is tagged {}", path, version, first_path, first));
+            }
+        }
+    }
+    Ok(warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn version(v: &str) -> GameVersion {
+        GameVersion::parse(v).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(version("0.8.2.10"), GameVersion(vec![0, 8, 2, 10]));
+        assert_eq!(version("0.8.2.10").to_string(), "0.8.2.10");
+        assert_eq!(GameVersion::parse("0.8.x"), Err(String::from("Invalid game version: 0.8.x")));
+    }
+
+    #[test]
+    fn test_untag() {
+        let file: Mapping = serde_yaml::from_str("game_version: 0.8.2.10\nbear:\n  hp: 100\n").unwrap();
+        let (v, data): (GameVersion, HashMap<String, HashMap<String, u64>>) = untag("characters.yaml", file).unwrap();
+        assert_eq!(v, version("0.8.2.10"));
+        assert_eq!(data["bear"]["hp"], 100);
+
+        let untagged: Mapping = serde_yaml::from_str("bear:\n  hp: 100\n").unwrap();
+        assert_eq!(
+            untag::<Mapping>("characters.yaml", untagged).map(|(v, _)| v),
+            Err(String::from("characters.yaml: missing game_version"))
+        );
+    }
+
+    #[test]
+    fn test_check() {
+        let (current, hotfix, release) = (version("0.8.2.10"), version("0.8.2.11"), version("0.9.1.2"));
+        assert_eq!(check(&[("characters.yaml", &current), ("effects.yaml", &current)]), Ok(Vec::new()));
+        assert_eq!(
+            check(&[("characters.yaml", &current), ("effects.yaml", &hotfix)]),
+            Ok(vec![String::from("effects.yaml is tagged 0.8.2.11 but characters.yaml is tagged 0.8.2.10")])
+        );
+        assert_eq!(
+            check(&[("characters.yaml", &current), ("effects.yaml", &hotfix), ("rulesets.yaml", &release)]),
+            Err(String::from("rulesets.yaml is tagged 0.9.1.2 but characters.yaml is tagged 0.8.2.10"))
+        );
+    }
+}